@@ -0,0 +1,284 @@
+use std::time::Duration;
+
+use crate::whisper::{is_cjk, Transcript, Utterance};
+
+pub struct Correction {
+    pub offset: i64,
+    pub drift: f64,
+}
+
+impl Correction {
+    pub fn apply(&self, transcript: &mut Transcript) {
+        transcript.retime(|t| t + self.offset + (t as f64 * self.drift).round() as i64);
+    }
+}
+
+fn best_offset(cues: &[Utterance], prefix: &[i64], center: i64, range: i64) -> (i64, i64) {
+    let len = prefix.len() as i64 - 1;
+    let overlap = |start: i64, end: i64| prefix[end.clamp(0, len) as usize] - prefix[start.clamp(0, len) as usize];
+    (center - range..=center + range)
+        .map(|offset| (offset, cues.iter().map(|u| overlap(u.start + offset, u.end + offset)).sum::<i64>()))
+        .max_by_key(|&(offset, score)| (score, -offset.abs()))
+        .unwrap_or((center, 0))
+}
+
+pub fn auto_sync(transcript: &Transcript, speech: &[bool], range: i64, drift: bool) -> Correction {
+    let prefix = speech.iter().fold(vec![0], |mut prefix, s| {
+        prefix.push(prefix.last().unwrap() + *s as i64);
+        prefix
+    });
+    let cues = &transcript.utterances;
+    let (offset, _) = best_offset(cues, &prefix, 0, range);
+    if !drift || cues.len() < 10 {
+        return Correction { offset, drift: 0.0 };
+    }
+
+    let points = cues
+        .chunks(cues.len().div_ceil(8).max(5))
+        .map(|chunk| {
+            let (local, _) = best_offset(chunk, &prefix, offset, 500);
+            let middle = (chunk[0].start + chunk[chunk.len() - 1].end) as f64 / 2.0;
+            (middle, local as f64)
+        })
+        .collect::<Vec<_>>();
+    let n = points.len() as f64;
+    let mean_t = points.iter().map(|p| p.0).sum::<f64>() / n;
+    let mean_o = points.iter().map(|p| p.1).sum::<f64>() / n;
+    let variance = points.iter().map(|p| (p.0 - mean_t).powi(2)).sum::<f64>();
+    if variance == 0.0 {
+        return Correction { offset, drift: 0.0 };
+    }
+    let drift = points.iter().map(|p| (p.0 - mean_t) * (p.1 - mean_o)).sum::<f64>() / variance;
+    Correction { offset: (mean_o - drift * mean_t).round() as i64, drift }
+}
+
+struct Unit {
+    text: String,
+    line: usize,
+    start: i64,
+    end: i64,
+}
+
+fn units<I: Iterator<Item=(char, usize, i64, i64)>>(chars: I) -> Vec<Unit> {
+    let mut units: Vec<Unit> = vec![];
+    let mut open = false;
+    for (c, line, start, end) in chars {
+        if is_cjk(c) && c.is_alphanumeric() {
+            units.push(Unit { text: c.to_string(), line, start, end });
+            open = false;
+        } else if c.is_alphanumeric() || c == '\'' {
+            match units.last_mut() {
+                Some(unit) if open && unit.line == line => {
+                    unit.text.extend(c.to_lowercase());
+                    unit.end = end;
+                }
+                _ => {
+                    units.push(Unit { text: c.to_lowercase().collect(), line, start, end });
+                    open = true;
+                }
+            }
+        } else {
+            open = false;
+        }
+    }
+    units
+}
+
+fn recognized(words: &[Utterance]) -> Vec<Unit> {
+    let mut group = 0;
+    units(words.iter().flat_map(|word| {
+        if word.text.starts_with(char::is_whitespace) {
+            group += 1;
+        }
+        let group = group;
+        let chars = word.text.trim().chars().collect::<Vec<_>>();
+        let len = chars.len().max(1) as i64;
+        let step = (word.end - word.start).max(0);
+        chars.into_iter().enumerate().map(move |(i, c)| {
+            let i = i as i64;
+            (c, group, word.start + step * i / len, word.start + step * (i + 1) / len)
+        })
+    }))
+}
+
+fn matches(reference: &[Unit], recognized: &[Unit]) -> Vec<(usize, usize)> {
+    let (n, m) = (reference.len(), recognized.len());
+    let band = 300 + n.abs_diff(m);
+    let range = |i: usize| {
+        let center = (i * m).checked_div(n).unwrap_or(0);
+        (center.saturating_sub(band), (center + band).min(m))
+    };
+    let mut cost = vec![vec![]; n + 1];
+    let mut trace = vec![vec![]; n + 1];
+    for i in 0..=n {
+        let (lo, hi) = range(i);
+        cost[i] = vec![u32::MAX; hi - lo + 1];
+        trace[i] = vec![0u8; hi - lo + 1];
+        for j in lo..=hi {
+            let get = |i: usize, j: usize| {
+                let (lo, hi) = range(i);
+                if j < lo || j > hi { u32::MAX } else { cost[i][j - lo] }
+            };
+            let mut best = (if i == 0 && j == 0 { 0 } else { u32::MAX }, 0);
+            if i > 0 && j > 0 {
+                let diagonal = get(i - 1, j - 1).saturating_add((reference[i - 1].text != recognized[j - 1].text) as u32);
+                best = best.min((diagonal, 1));
+            }
+            if i > 0 {
+                best = best.min((get(i - 1, j).saturating_add(1), 2));
+            }
+            if j > lo {
+                best = best.min((cost[i][j - 1 - lo].saturating_add(1), 3));
+            }
+            cost[i][j - lo] = best.0;
+            trace[i][j - lo] = best.1;
+        }
+    }
+
+    let mut pairs = vec![];
+    let (mut i, mut j) = (n, m);
+    while i > 0 || j > 0 {
+        let (lo, _) = range(i);
+        match trace[i].get(j.wrapping_sub(lo)).copied().unwrap_or(0) {
+            1 => {
+                pairs.push((i - 1, j - 1));
+                i -= 1;
+                j -= 1;
+            }
+            2 => i -= 1,
+            3 => j -= 1,
+            _ => break,
+        }
+    }
+    pairs.reverse();
+    pairs
+}
+
+fn strip_tags(line: &str) -> &str {
+    let mut rest = line.trim();
+    while let Some((_, tail)) = rest.strip_prefix('[').and_then(|r| r.split_once(']')) {
+        rest = tail.trim_start();
+    }
+    rest
+}
+
+pub fn align_text(text: &str, words: &[Utterance]) -> Transcript {
+    let lines = text.lines().map(strip_tags).filter(|l| !l.is_empty()).collect::<Vec<_>>();
+    let reference = units(lines.iter().enumerate().flat_map(|(line, text)| text.chars().map(move |c| (c, line, 0, 0))));
+    let recognized = recognized(words);
+
+    let mut times: Vec<Option<(i64, i64)>> = vec![None; lines.len()];
+    for (r, w) in matches(&reference, &recognized) {
+        let (start, end) = (recognized[w].start, recognized[w].end);
+        let time = times[reference[r].line].get_or_insert((start, end));
+        *time = (time.0.min(start), time.1.max(end));
+    }
+
+    let mut utterances = Vec::with_capacity(lines.len());
+    let mut i = 0;
+    while i < lines.len() {
+        if let Some((start, end)) = times[i] {
+            utterances.push(Utterance { start, end, text: lines[i].to_string() });
+            i += 1;
+            continue;
+        }
+        let gap = (i..lines.len()).take_while(|&j| times[j].is_none()).count();
+        let from = utterances.last().map(|u: &Utterance| u.end).unwrap_or_default();
+        let to = times.get(i + gap).copied().flatten().map(|t| t.0).unwrap_or(from + 300 * gap as i64);
+        let step = (to - from).max(0) / gap as i64;
+        for k in 0..gap {
+            let start = from + step * k as i64;
+            utterances.push(Utterance { start, end: start + step, text: lines[i + k].to_string() });
+        }
+        i += gap;
+    }
+    for i in 1..utterances.len() {
+        if utterances[i - 1].end > utterances[i].start {
+            utterances[i - 1].end = utterances[i].start.max(utterances[i - 1].start);
+        }
+    }
+
+    Transcript {
+        processing_time: Duration::ZERO,
+        utterances,
+        word_utterances: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word(start: i64, end: i64, text: &str) -> Utterance {
+        Utterance { start, end, text: text.to_string() }
+    }
+
+    fn cues(transcript: &Transcript) -> Vec<(i64, i64, &str)> {
+        transcript.utterances.iter().map(|u| (u.start, u.end, u.text.as_str())).collect()
+    }
+
+    #[test]
+    fn align_interpolates_skipped_lines() {
+        let words = vec![word(0, 50, " hello"), word(50, 100, " world"), word(300, 350, " good"), word(350, 400, " night")];
+        let transcript = align_text("hello world\nskipped line\ngood night", &words);
+        assert_eq!(cues(&transcript), [(0, 100, "hello world"), (100, 300, "skipped line"), (300, 400, "good night")]);
+    }
+
+    #[test]
+    fn align_ignores_extra_recognized_words() {
+        let words = vec![
+            word(0, 50, " hello"),
+            word(50, 80, " um"),
+            word(80, 150, " world"),
+            word(200, 250, " good"),
+            word(250, 300, " night"),
+        ];
+        let transcript = align_text("hello world\ngood night", &words);
+        assert_eq!(cues(&transcript), [(0, 150, "hello world"), (200, 300, "good night")]);
+    }
+
+    #[test]
+    fn align_cjk_without_spaces() {
+        let words = vec![word(0, 100, "你好"), word(100, 200, "世界"), word(300, 400, "再见")];
+        let transcript = align_text("你好世界\n再见", &words);
+        assert_eq!(cues(&transcript), [(0, 200, "你好世界"), (300, 400, "再见")]);
+    }
+
+    #[test]
+    fn align_strips_lrc_tags() {
+        let words = vec![word(0, 50, " hello"), word(50, 100, " world"), word(200, 250, " good"), word(250, 300, " night")];
+        let transcript = align_text("[ti:Song]\n[00:01.00]hello world\n[00:05.00][00:09.00] good night\n", &words);
+        assert_eq!(cues(&transcript), [(0, 100, "hello world"), (200, 300, "good night")]);
+    }
+
+    #[test]
+    fn align_clamps_overlapping_lines() {
+        let words = vec![word(0, 100, " hello"), word(80, 150, " there")];
+        let transcript = align_text("hello\nthere", &words);
+        assert_eq!(cues(&transcript), [(0, 80, "hello"), (80, 150, "there")]);
+    }
+
+    fn synthetic(f: impl Fn(i64) -> i64) -> (Transcript, Vec<bool>) {
+        let utterances = (0..40).map(|i| word(i * 1000 + (i * i % 7) * 40, i * 1000 + (i * i % 7) * 40 + 300, "")).collect::<Vec<_>>();
+        let mut speech = vec![false; 45000];
+        for u in utterances.iter() {
+            speech[f(u.start) as usize..f(u.end) as usize].fill(true);
+        }
+        (Transcript { processing_time: Duration::ZERO, utterances, word_utterances: None }, speech)
+    }
+
+    #[test]
+    fn auto_sync_finds_constant_offset() {
+        let (transcript, speech) = synthetic(|t| t + 150);
+        let correction = auto_sync(&transcript, &speech, 6000, false);
+        assert_eq!((correction.offset, correction.drift), (150, 0.0));
+    }
+
+    #[test]
+    fn auto_sync_estimates_linear_drift() {
+        let (transcript, speech) = synthetic(|t| 100 + t + t / 200);
+        let correction = auto_sync(&transcript, &speech, 6000, true);
+        assert!((correction.drift - 0.005).abs() < 0.0005, "{}", correction.drift);
+        assert!((correction.offset - 100).abs() <= 10, "{}", correction.offset);
+    }
+}
@@ -0,0 +1,177 @@
+use std::fmt::Display;
+
+use serde::Serialize;
+
+use crate::config::Segmentation;
+use crate::whisper::{rewrap, Transcript, Utterance, width};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Rule {
+    Overlap,
+    Duration,
+    BeyondAudio,
+    ReadingSpeed,
+    LineLength,
+    Empty,
+}
+
+impl Display for Rule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let key = match self {
+            Self::Overlap => "时间重叠",
+            Self::Duration => "时长无效",
+            Self::BeyondAudio => "超出音频",
+            Self::ReadingSpeed => "阅读速度过快",
+            Self::LineLength => "行过长",
+            Self::Empty => "空字幕",
+        };
+        write!(f, "{key}")
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Issue {
+    pub index: usize,
+    pub start: i64,
+    pub end: i64,
+    pub rule: Rule,
+    pub message: String,
+}
+
+pub fn check(transcript: &Transcript, limits: &Segmentation, length: Option<i64>) -> Vec<Issue> {
+    let mut issues = vec![];
+    for (i, u) in transcript.utterances.iter().enumerate() {
+        let mut issue = |rule, message: String| issues.push(Issue { index: i + 1, start: u.start, end: u.end, rule, message });
+        if u.text.trim().is_empty() {
+            issue(Rule::Empty, "字幕为空".to_string());
+            continue;
+        }
+        if u.end <= u.start {
+            issue(Rule::Duration, format!("时长 {:.2}s", (u.end - u.start) as f64 / 100.0));
+        }
+        if let Some(next) = transcript.utterances.get(i + 1) {
+            if next.start < u.end {
+                issue(Rule::Overlap, format!("与下一条重叠 {:.2}s", (u.end - next.start) as f64 / 100.0));
+            }
+        }
+        if let Some(length) = length {
+            if u.end > length {
+                issue(Rule::BeyondAudio, format!("超出音频结尾 {:.2}s", (u.end - length) as f64 / 100.0));
+            }
+        }
+        if u.end > u.start {
+            let cps = width(&u.text) as f64 / ((u.end - u.start) as f64 / 100.0);
+            if cps > limits.cps {
+                issue(Rule::ReadingSpeed, format!("{:.1} 字符/秒", cps));
+            }
+        }
+        let longest = u.text.lines().map(width).max().unwrap_or_default();
+        let lines = u.text.lines().count();
+        if longest > limits.chars_per_line || lines > limits.lines {
+            issue(Rule::LineLength, format!("{} 行, 最长 {} 字符", lines, longest));
+        }
+    }
+    issues
+}
+
+fn split(u: Utterance, limits: &Segmentation) -> Vec<Utterance> {
+    if u.text.lines().map(width).max().unwrap_or_default() <= limits.chars_per_line && u.text.lines().count() <= limits.lines {
+        return vec![u];
+    }
+    let text = rewrap(&u.text, limits.chars_per_line);
+    let chunks = text.lines().collect::<Vec<_>>().chunks(limits.lines.max(1)).map(|c| c.join("\n")).collect::<Vec<_>>();
+    let total = chunks.iter().map(|c| width(c)).sum::<usize>().max(1) as i64;
+    let span = (u.end - u.start).max(total);
+    let mut used = 0;
+    chunks
+        .into_iter()
+        .map(|text| {
+            let start = u.start + span * used / total;
+            used += width(&text) as i64;
+            Utterance { start, end: u.start + span * used / total, text }
+        })
+        .collect()
+}
+
+pub fn fix(transcript: &mut Transcript, limits: &Segmentation, length: Option<i64>) {
+    let mut merged: Vec<Utterance> = vec![];
+    let mut utterances = std::mem::take(&mut transcript.utterances);
+    utterances.retain(|u| !u.text.trim().is_empty() && !matches!(length, Some(l) if u.start >= l));
+    utterances.sort_by_key(|u| u.start);
+    for u in utterances {
+        match merged.last_mut() {
+            Some(last) if last.start == u.start => {
+                last.end = last.end.max(u.end);
+                last.text = format!("{}\n{}", last.text.trim(), u.text.trim());
+            }
+            _ => merged.push(u),
+        }
+    }
+    let mut utterances = merged.into_iter().flat_map(|u| split(u, limits)).collect::<Vec<_>>();
+    utterances.sort_by_key(|u| u.start);
+    for i in 0..utterances.len() {
+        let limit = utterances.get(i + 1).map(|u| u.start).into_iter().chain(length).min().unwrap_or(i64::MAX);
+        let u = &mut utterances[i];
+        let minimum = (width(&u.text) as f64 / limits.cps * 100.0).ceil() as i64;
+        u.end = u.end.max(u.start + minimum).min(limit).max(u.start + 1);
+    }
+    transcript.utterances = utterances;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn utterance(start: i64, end: i64, text: &str) -> Utterance {
+        Utterance { start, end, text: text.to_string() }
+    }
+
+    fn transcript(utterances: Vec<Utterance>) -> Transcript {
+        Transcript { processing_time: Duration::ZERO, utterances, word_utterances: None }
+    }
+
+    fn cues(transcript: &Transcript) -> Vec<(i64, i64, &str)> {
+        transcript.utterances.iter().map(|u| (u.start, u.end, u.text.as_str())).collect()
+    }
+
+    fn limits(chars_per_line: usize, lines: usize) -> Segmentation {
+        Segmentation { chars_per_line, lines, cps: 20.0, ..Default::default() }
+    }
+
+    #[test]
+    fn fix_clamps_overlaps() {
+        let mut t = transcript(vec![utterance(0, 300, "one"), utterance(200, 400, "two")]);
+        let rules = check(&t, &limits(42, 2), None).iter().map(|i| i.rule).collect::<Vec<_>>();
+        assert_eq!(rules, [Rule::Overlap]);
+        fix(&mut t, &limits(42, 2), None);
+        assert_eq!(cues(&t), [(0, 200, "one"), (200, 400, "two")]);
+        assert!(check(&t, &limits(42, 2), None).is_empty());
+    }
+
+    #[test]
+    fn fix_splits_long_cues_by_width() {
+        let mut t = transcript(vec![utterance(0, 400, "aaaa bbbb cccc dddd")]);
+        assert_eq!(check(&t, &limits(10, 1), None)[0].rule, Rule::LineLength);
+        fix(&mut t, &limits(10, 1), None);
+        assert_eq!(cues(&t), [(0, 200, "aaaa bbbb"), (200, 400, "cccc dddd")]);
+    }
+
+    #[test]
+    fn fix_merges_cues_with_same_start() {
+        let mut t = transcript(vec![utterance(100, 200, "one"), utterance(100, 300, "two"), utterance(400, 500, "three")]);
+        fix(&mut t, &limits(42, 2), None);
+        assert_eq!(cues(&t), [(100, 300, "one\ntwo"), (400, 500, "three")]);
+    }
+
+    #[test]
+    fn fix_respects_audio_length() {
+        let mut t = transcript(vec![utterance(0, 200, "one"), utterance(400, 700, "two"), utterance(600, 700, "three")]);
+        let issues = check(&t, &limits(42, 2), Some(500));
+        assert_eq!(issues.iter().filter(|i| i.rule == Rule::BeyondAudio).count(), 2);
+        fix(&mut t, &limits(42, 2), Some(500));
+        assert_eq!(cues(&t), [(0, 200, "one"), (400, 500, "two")]);
+    }
+}
@@ -0,0 +1,609 @@
+use std::env::temp_dir;
+use std::path::PathBuf;
+use std::time::Instant;
+
+use anyhow::anyhow;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+
+use crate::align::{align_text, auto_sync};
+use crate::check;
+use crate::config::{Aspect, AudioCodec, Background, Canvas, Container, Fill, GeneratedBackground, KenBurns, Language, Logo, MergeConfig, Model, Motion, Overlay, Position, Preset, Resolution, Segmentation, SlideTiming, Slideshow, Speed, SubtitleMode, SubtitleStyle, Template, TitleCard, VideoCodec, Visualization, Visualizer, Watermark};
+use crate::conv::{Files, Slide, Subtitle};
+use crate::utils;
+use crate::utils::merge_files;
+use crate::whisper::{Format, parse_time, Transcript, Whisper};
+
+#[derive(Parser)]
+#[command(name = "conv")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// 合并音频/图片/字幕生成视频
+    Merge {
+        #[command(flatten)]
+        args: MergeArgs,
+        /// 输出路径, 默认与音频同名
+        #[arg(long)]
+        output: Option<PathBuf>,
+        /// 静态图片加速: 低帧率输入, 只编码字幕变化的帧(需要FFmpeg 5.1+)
+        #[arg(long)]
+        still: bool,
+        /// 只渲染从该时间(秒)开始的片段用于预览, 默认输出为 *.preview.*
+        #[arg(long)]
+        preview: Option<f64>,
+        /// 预览片段时长(秒), 10-30
+        #[arg(long, default_value_t = 15.0)]
+        preview_length: f64,
+    },
+    /// 对比普通编码与静态图片加速编码的耗时
+    Bench {
+        #[command(flatten)]
+        args: MergeArgs,
+    },
+    /// 调整字幕时间: 平移, 两点拉伸, 帧率转换
+    Retime {
+        input: PathBuf,
+        /// 平移(秒), 可为负数
+        #[arg(long, allow_negative_numbers = true)]
+        shift: Option<f64>,
+        /// 同步点, 格式为 原时间=新时间, 如 00:01:02.50=00:01:03.10, 需指定两次
+        #[arg(long, value_parser = parse_sync)]
+        sync: Vec<(i64, i64)>,
+        /// 帧率转换, 格式为 原帧率:新帧率, 如 23.976:25
+        #[arg(long, value_parser = parse_framerate)]
+        fps: Option<(f64, f64)>,
+        #[arg(long, value_enum)]
+        format: Option<Format>,
+        /// 输出路径, 默认为 *.retimed.*
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+    /// 检查字幕: 时间重叠, 无效时长, 超出音频, 阅读速度, 行长, 空字幕
+    Check {
+        input: PathBuf,
+        /// 用于检查是否超出音频长度
+        #[arg(long)]
+        audio: Option<PathBuf>,
+        #[arg(long, default_value_t = 42)]
+        cpl: usize,
+        #[arg(long, default_value_t = 2)]
+        lines: usize,
+        #[arg(long, value_parser = parse_positive, default_value_t = 20.0)]
+        cps: f64,
+        /// 以JSON输出问题列表
+        #[arg(long)]
+        json: bool,
+        /// 自动修复并写入 --output, 默认为 *.fixed.*
+        #[arg(long)]
+        fix: bool,
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+    /// 按行长/行数/时长/阅读速度重新分段字幕
+    Resegment {
+        input: PathBuf,
+        /// 每行最多字符数(半角计, 中日韩文字计为2)
+        #[arg(long, default_value_t = 42)]
+        cpl: usize,
+        /// 每条字幕最多行数
+        #[arg(long, default_value_t = 2)]
+        lines: usize,
+        /// 每条字幕最长时长(秒)
+        #[arg(long, default_value_t = 7.0)]
+        max_duration: f64,
+        /// 最大阅读速度(字符/秒, 半角计)
+        #[arg(long, value_parser = parse_positive, default_value_t = 20.0)]
+        cps: f64,
+        #[arg(long, value_enum)]
+        format: Option<Format>,
+        /// 输出路径, 默认为 *.resegmented.*
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+    /// 将已有的歌词/文稿与音频对齐, 输出带时间轴的字幕
+    Align {
+        #[arg(long)]
+        audio: PathBuf,
+        /// 纯文本文件, 每行一句
+        #[arg(long)]
+        text: PathBuf,
+        #[arg(long, value_enum, default_value = "auto")]
+        lang: Language,
+        #[arg(long, value_enum, default_value = "medium")]
+        model: Model,
+        /// 输出格式, 可重复, 输出到音频同目录
+        #[arg(long, value_enum, default_values = ["lrc", "srt"])]
+        format: Vec<Format>,
+    },
+    /// 根据音频中的语音自动校正字幕偏移
+    Sync {
+        input: PathBuf,
+        #[arg(long)]
+        audio: PathBuf,
+        /// 同时校正速度漂移
+        #[arg(long)]
+        drift: bool,
+        /// 最大偏移搜索范围(秒)
+        #[arg(long, default_value_t = 60.0)]
+        range: f64,
+        #[arg(long, value_enum)]
+        format: Option<Format>,
+        /// 输出路径, 默认为 *.synced.*
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Args)]
+pub struct MergeArgs {
+    #[arg(long)]
+    audio: PathBuf,
+    /// 背景图片/视频/GIF, 可重复以生成幻灯片; 省略时使用 --background 生成背景
+    #[arg(long)]
+    image: Vec<PathBuf>,
+    /// 未指定图片时生成的背景
+    #[arg(long, value_enum, default_value = "color")]
+    background: Background,
+    /// 生成背景的颜色, 渐变时为起始颜色
+    #[arg(long, value_parser = parse_color, default_value = "#202028")]
+    background_color: [u8; 3],
+    /// 渐变的结束颜色
+    #[arg(long, value_parser = parse_color, default_value = "#403060")]
+    background_color2: [u8; 3],
+    /// 在生成背景上显示标题和艺术家
+    #[arg(long)]
+    title_text: bool,
+    #[arg(long, value_parser = parse_color, default_value = "#ffffff")]
+    title_color: [u8; 3],
+    /// 幻灯片切换方式
+    #[arg(long, value_enum, default_value = "even")]
+    slide_timing: SlideTiming,
+    /// 每张图片的开始时间(秒), 用于 --slide-timing manual, 以逗号分隔
+    #[arg(long, value_delimiter = ',')]
+    slide_start: Vec<f64>,
+    /// 淡入淡出时长(秒)
+    #[arg(long, default_value_t = 0.0)]
+    crossfade: f64,
+    /// 烧录字幕, 格式为 [语言:]路径
+    #[arg(long, value_parser = parse_subtitle)]
+    burn: Option<Subtitle>,
+    /// 软字幕, 可重复, 格式为 [语言:]路径
+    #[arg(long, value_parser = parse_subtitle)]
+    soft: Vec<Subtitle>,
+    #[arg(long, value_enum)]
+    preset: Option<Preset>,
+    #[arg(long, value_enum)]
+    container: Option<Container>,
+    #[arg(long, value_enum)]
+    video_codec: Option<VideoCodec>,
+    #[arg(long)]
+    crf: Option<u8>,
+    /// 视频码率(k), 设置后忽略CRF
+    #[arg(long)]
+    video_bitrate: Option<u32>,
+    #[arg(long, value_enum)]
+    speed: Option<Speed>,
+    #[arg(long, value_enum)]
+    audio_codec: Option<AudioCodec>,
+    /// 音频码率(k)
+    #[arg(long)]
+    audio_bitrate: Option<u32>,
+    #[arg(long)]
+    framerate: Option<u32>,
+    #[arg(long, value_enum, default_value = "1080p")]
+    resolution: Resolution,
+    /// 画面比例, 竖屏时字幕自动上移到安全区域
+    #[arg(long, value_enum, default_value = "16:9")]
+    aspect: Aspect,
+    /// 自定义分辨率宽度
+    #[arg(long, default_value_t = 1920)]
+    width: u32,
+    /// 自定义分辨率高度
+    #[arg(long, default_value_t = 1080)]
+    height: u32,
+    #[arg(long, value_enum, default_value = "color")]
+    fill: Fill,
+    /// 背景图片的缩放/平移效果
+    #[arg(long, value_enum, default_value = "none")]
+    motion: Motion,
+    /// 缩放/平移幅度, 如 1.2
+    #[arg(long, default_value_t = 1.2)]
+    motion_amount: f64,
+    /// 一次往返动画的时长(秒)
+    #[arg(long, default_value_t = 20.0)]
+    motion_period: f64,
+    /// 音频可视化样式
+    #[arg(long, value_enum, default_value = "none")]
+    visualizer: Visualizer,
+    #[arg(long, value_enum, default_value = "bottom")]
+    visualizer_position: Position,
+    /// 可视化宽度(画面宽度的百分比)
+    #[arg(long, default_value_t = 100)]
+    visualizer_width: u32,
+    /// 可视化高度(画面高度的百分比)
+    #[arg(long, default_value_t = 20)]
+    visualizer_height: u32,
+    #[arg(long, value_parser = parse_color, default_value = "#ffffff")]
+    visualizer_color: [u8; 3],
+    #[arg(long, value_parser = parse_opacity, default_value_t = 0.8)]
+    visualizer_opacity: f32,
+    /// 烧录字幕字体, 设置任一 --subtitle-* 参数即使用自定义字幕样式
+    #[arg(long)]
+    subtitle_font: Option<String>,
+    #[arg(long)]
+    subtitle_size: Option<u32>,
+    #[arg(long, value_parser = parse_color)]
+    subtitle_color: Option<[u8; 3]>,
+    #[arg(long, value_parser = parse_color)]
+    subtitle_outline_color: Option<[u8; 3]>,
+    /// 描边宽度
+    #[arg(long)]
+    subtitle_outline: Option<f32>,
+    /// 阴影距离
+    #[arg(long)]
+    subtitle_shadow: Option<f32>,
+    /// 垂直边距
+    #[arg(long)]
+    subtitle_margin: Option<u32>,
+    #[arg(long, value_enum)]
+    subtitle_alignment: Option<Position>,
+    #[arg(long)]
+    subtitle_bold: bool,
+    /// 片头标题文字
+    #[arg(long)]
+    card_text: Option<String>,
+    #[arg(long, default_value = "Microsoft YaHei UI")]
+    card_font: String,
+    #[arg(long, default_value_t = 72)]
+    card_size: u32,
+    #[arg(long, value_parser = parse_color, default_value = "#ffffff")]
+    card_color: [u8; 3],
+    #[arg(long, value_enum, default_value = "center")]
+    card_position: Position,
+    /// 片头标题显示的开始时间(秒)
+    #[arg(long, default_value_t = 0.0)]
+    card_start: f64,
+    /// 片头标题显示的结束时间(秒)
+    #[arg(long, default_value_t = 5.0)]
+    card_end: f64,
+    /// 全程显示的水印文字
+    #[arg(long)]
+    watermark: Option<String>,
+    #[arg(long, default_value = "Microsoft YaHei UI")]
+    watermark_font: String,
+    #[arg(long, default_value_t = 28)]
+    watermark_size: u32,
+    #[arg(long, value_parser = parse_color, default_value = "#ffffff")]
+    watermark_color: [u8; 3],
+    #[arg(long, value_parser = parse_opacity, default_value_t = 0.5)]
+    watermark_opacity: f32,
+    #[arg(long, value_enum, default_value = "bottom-right")]
+    watermark_position: Position,
+    /// Logo图片
+    #[arg(long)]
+    logo: Option<PathBuf>,
+    #[arg(long, value_enum, default_value = "top-right")]
+    logo_position: Position,
+    /// Logo宽度(画面宽度的百分比)
+    #[arg(long, default_value_t = 10)]
+    logo_scale: u32,
+    #[arg(long, value_parser = parse_opacity, default_value_t = 0.8)]
+    logo_opacity: f32,
+    /// 使用已保存的叠加层模板, 忽略上面的标题/水印/Logo参数(--logo 除外)
+    #[arg(long)]
+    template: Option<String>,
+    /// 将标题/水印/Logo参数保存为模板
+    #[arg(long)]
+    save_template: Option<String>,
+    /// 填充颜色, 如 #000000
+    #[arg(long, value_parser = parse_color, default_value = "#000000")]
+    color: [u8; 3],
+}
+
+impl MergeArgs {
+    fn files(&self) -> Files {
+        let mut subtitles = vec![];
+        if let Some(ref burn) = self.burn {
+            subtitles.push(Subtitle { mode: SubtitleMode::Burn, ..burn.clone() });
+        }
+        subtitles.extend(self.soft.iter().cloned());
+        Files {
+            audio: Some(self.audio.clone()),
+            images: self.image
+                .iter()
+                .enumerate()
+                .map(|(i, path)| Slide { path: path.clone(), start: self.slide_start.get(i).copied().unwrap_or_default() })
+                .collect(),
+            subtitles,
+            logo: None,
+        }
+    }
+
+    fn config(&self) -> MergeConfig {
+        let preset = self.preset.unwrap_or(Preset::Custom);
+        let mut encoder = preset.encoder();
+        if let Some(container) = self.container {
+            encoder.container = container;
+        }
+        if let Some(video_codec) = self.video_codec {
+            encoder.video_codec = video_codec;
+        }
+        if let Some(crf) = self.crf {
+            encoder.crf = crf;
+        }
+        if let Some(video_bitrate) = self.video_bitrate {
+            encoder.video_bitrate = video_bitrate;
+        }
+        if let Some(speed) = self.speed {
+            encoder.speed = speed;
+        }
+        if let Some(audio_codec) = self.audio_codec {
+            encoder.audio_codec = audio_codec;
+        }
+        if let Some(audio_bitrate) = self.audio_bitrate {
+            encoder.audio_bitrate = audio_bitrate;
+        }
+        if let Some(framerate) = self.framerate {
+            encoder.framerate = framerate;
+        }
+        let canvas = Canvas {
+            resolution: self.resolution,
+            aspect: self.aspect,
+            width: self.width,
+            height: self.height,
+            fill: self.fill,
+            color: self.color,
+        };
+        let slideshow = Slideshow {
+            timing: self.slide_timing,
+            crossfade: self.crossfade,
+        };
+        let ken_burns = KenBurns {
+            motion: self.motion,
+            amount: self.motion_amount,
+            period: self.motion_period,
+        };
+        let visualization = Visualization {
+            kind: self.visualizer,
+            position: self.visualizer_position,
+            width: self.visualizer_width,
+            height: self.visualizer_height,
+            color: self.visualizer_color,
+            opacity: self.visualizer_opacity,
+        };
+        let generated = GeneratedBackground {
+            kind: self.background,
+            color: self.background_color,
+            color2: self.background_color2,
+            text: self.title_text,
+            text_color: self.title_color,
+        };
+        let default = SubtitleStyle::default();
+        let subtitle_style = SubtitleStyle {
+            enabled: self.subtitle_font.is_some()
+                || self.subtitle_size.is_some()
+                || self.subtitle_color.is_some()
+                || self.subtitle_outline_color.is_some()
+                || self.subtitle_outline.is_some()
+                || self.subtitle_shadow.is_some()
+                || self.subtitle_margin.is_some()
+                || self.subtitle_alignment.is_some()
+                || self.subtitle_bold,
+            font: self.subtitle_font.clone().unwrap_or(default.font),
+            size: self.subtitle_size.unwrap_or(default.size),
+            color: self.subtitle_color.unwrap_or(default.color),
+            outline_color: self.subtitle_outline_color.unwrap_or(default.outline_color),
+            outline: self.subtitle_outline.unwrap_or(default.outline),
+            shadow: self.subtitle_shadow.unwrap_or(default.shadow),
+            margin: self.subtitle_margin.unwrap_or(default.margin),
+            alignment: self.subtitle_alignment.unwrap_or(default.alignment),
+            bold: self.subtitle_bold,
+        };
+        MergeConfig { preset, encoder, canvas, slideshow, ken_burns, visualization, generated, subtitle_style, ..Default::default() }
+    }
+
+    fn template(&self) -> anyhow::Result<Template> {
+        let mut template = match self.template {
+            Some(ref name) => Template::load(name)?,
+            None => Template {
+                overlay: Overlay {
+                    title: TitleCard {
+                        enabled: self.card_text.is_some(),
+                        text: self.card_text.clone().unwrap_or_default(),
+                        font: self.card_font.clone(),
+                        size: self.card_size,
+                        color: self.card_color,
+                        position: self.card_position,
+                        start: self.card_start,
+                        end: self.card_end,
+                    },
+                    watermark: Watermark {
+                        enabled: self.watermark.is_some(),
+                        text: self.watermark.clone().unwrap_or_default(),
+                        font: self.watermark_font.clone(),
+                        size: self.watermark_size,
+                        color: self.watermark_color,
+                        opacity: self.watermark_opacity,
+                        position: self.watermark_position,
+                    },
+                    logo: Logo {
+                        position: self.logo_position,
+                        scale: self.logo_scale,
+                        opacity: self.logo_opacity,
+                    },
+                },
+                logo: None,
+            },
+        };
+        if let Some(ref logo) = self.logo {
+            template.logo = Some(logo.clone());
+        }
+        if let Some(ref name) = self.save_template {
+            template.save(name)?;
+        }
+        Ok(template)
+    }
+}
+
+fn parse_subtitle(s: &str) -> Result<Subtitle, String> {
+    let (lang, path) = match s.split_once(':') {
+        Some((lang, path)) => match Language::from_str(lang, true) {
+            Ok(lang) => (lang, path),
+            Err(_) => (Language::Auto, s),
+        },
+        None => (Language::Auto, s),
+    };
+    Ok(Subtitle { path: PathBuf::from(path), lang, mode: SubtitleMode::Soft })
+}
+
+fn parse_color(s: &str) -> Result<[u8; 3], String> {
+    let hex = s.trim_start_matches('#');
+    let rgb = u32::from_str_radix(hex, 16).map_err(|e| e.to_string())?;
+    if hex.len() != 6 {
+        return Err("expected RRGGBB".to_string());
+    }
+    Ok([(rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8])
+}
+
+fn parse_opacity(s: &str) -> Result<f32, String> {
+    match s.parse::<f32>().map_err(|e| e.to_string())? {
+        opacity if (0.0..=1.0).contains(&opacity) => Ok(opacity),
+        _ => Err("expected 0.0-1.0".to_string()),
+    }
+}
+
+fn parse_positive(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>().map_err(|e| e.to_string())? {
+        value if value > 0.0 => Ok(value),
+        _ => Err("expected a positive number".to_string()),
+    }
+}
+
+fn parse_sync(s: &str) -> Result<(i64, i64), String> {
+    let (from, to) = s.split_once('=').ok_or("expected FROM=TO")?;
+    match (parse_time(from), parse_time(to)) {
+        (Some(from), Some(to)) => Ok((from, to)),
+        _ => Err("invalid time".to_string()),
+    }
+}
+
+fn parse_framerate(s: &str) -> Result<(f64, f64), String> {
+    let (from, to) = s.split_once(':').ok_or("expected FROM:TO")?;
+    Ok((from.parse().map_err(|_| "invalid framerate")?, to.parse().map_err(|_| "invalid framerate")?))
+}
+
+fn format_time(time: i64) -> String {
+    format!("{:02}:{:02}:{:02}.{:02}", time / 360000, time / 6000 % 60, time / 100 % 60, time % 100)
+}
+
+pub async fn run(command: Command) -> anyhow::Result<()> {
+    match command {
+        Command::Merge { args, output, still, preview, preview_length } => {
+            let template = args.template()?;
+            let excerpt = preview.map(|start| (start.max(0.0), preview_length.clamp(10.0, 30.0)));
+            let config = MergeConfig { still, overlay: template.overlay, excerpt, ..args.config() };
+            let files = Files { logo: template.logo, ..args.files() };
+            let output = merge_files(&files, &config, output)?;
+            println!("{}", output.display());
+        }
+        Command::Bench { args } => {
+            let template = args.template()?;
+            let files = Files { logo: template.logo, ..args.files() };
+            let mut results = vec![];
+            for still in [false, true] {
+                let config = MergeConfig { still, overlay: template.overlay.clone(), ..args.config() };
+                let output = temp_dir().join(format!("{}.{}", uuid::Uuid::new_v4(), config.encoder.container.extension()));
+                let st = Instant::now();
+                merge_files(&files, &config, Some(output.clone()))?;
+                let elapsed = st.elapsed();
+                let size = std::fs::metadata(&output)?.len();
+                std::fs::remove_file(&output)?;
+                println!("{}: {:.2}s, {:.2}MB", if still { "静态图片加速" } else { "普通编码" }, elapsed.as_secs_f64(), size as f64 / 1024.0 / 1024.0);
+                results.push(elapsed);
+            }
+            println!("加速: {:.1}x", results[0].as_secs_f64() / results[1].as_secs_f64());
+        }
+        Command::Retime { input, shift, sync, fps, format, output } => {
+            let mut transcript = Transcript::read_file(&input)?;
+            if let Some(shift) = shift {
+                transcript.shift((shift * 100.0).round() as i64);
+            }
+            match sync[..] {
+                [] => {}
+                [a, b] => transcript.stretch(a, b)?,
+                _ => return Err(anyhow!("--sync requires exactly two sync points")),
+            }
+            if let Some((from, to)) = fps {
+                transcript.convert_framerate(from, to)?;
+            }
+            let format = format.or_else(|| Format::from_path(&input)).unwrap_or(Format::Srt);
+            let output = output.unwrap_or_else(|| input.with_extension(format!("retimed.{}", format.extension())));
+            transcript.save(&output, format)?;
+            println!("{}", output.display());
+        }
+        Command::Check { input, audio, cpl, lines, cps, json, fix, output } => {
+            let mut transcript = Transcript::read_file(&input)?;
+            let limits = Segmentation { chars_per_line: cpl, lines, cps, ..Default::default() };
+            let length = match audio {
+                Some(audio) => Some((utils::duration(audio)? * 100.0) as i64),
+                None => None,
+            };
+            if fix {
+                check::fix(&mut transcript, &limits, length);
+                let format = Format::from_path(&input).unwrap_or(Format::Srt);
+                let output = output.unwrap_or_else(|| input.with_extension(format!("fixed.{}", format.extension())));
+                transcript.save(&output, format)?;
+                eprintln!("{}", output.display());
+            }
+            let issues = check::check(&transcript, &limits, length);
+            if json {
+                println!("{}", serde_json::to_string_pretty(&issues)?);
+            } else {
+                for issue in issues.iter() {
+                    println!("{}\t{}\t{}\t{}", issue.index, format_time(issue.start), issue.rule, issue.message);
+                }
+            }
+            if !issues.is_empty() {
+                return match fix {
+                    true => Err(anyhow!("{} issues cannot be fixed automatically", issues.len())),
+                    false => Err(anyhow!("{} issues found", issues.len())),
+                };
+            }
+        }
+        Command::Resegment { input, cpl, lines, max_duration, cps, format, output } => {
+            let segmentation = Segmentation { enabled: true, chars_per_line: cpl, lines, duration: max_duration, cps };
+            let transcript = Transcript::read_file(&input)?.resegment(&segmentation);
+            let format = format.or_else(|| Format::from_path(&input)).unwrap_or(Format::Srt);
+            let output = output.unwrap_or_else(|| input.with_extension(format!("resegmented.{}", format.extension())));
+            transcript.save(&output, format)?;
+            println!("{}", output.display());
+        }
+        Command::Align { audio, text, lang, model, format } => {
+            let text = std::fs::read_to_string(text)?;
+            let mut whisper = Whisper::new(lang, model).await?;
+            let words = whisper.transcribe(&audio, false, true)?.word_utterances.unwrap_or_default();
+            let transcript = align_text(&text, &words);
+            for format in format {
+                let output = audio.with_extension(format.extension());
+                transcript.save(&output, format)?;
+                println!("{}", output.display());
+            }
+        }
+        Command::Sync { input, audio, drift, range, format, output } => {
+            let mut transcript = Transcript::read_file(&input)?;
+            let speech = utils::speech(&utils::read_file(&audio)?);
+            let sync = auto_sync(&transcript, &speech, (range * 100.0) as i64, drift);
+            sync.apply(&mut transcript);
+            eprintln!("偏移: {:.2}s, 速度: x{:.5}", sync.offset as f64 / 100.0, 1.0 + sync.drift);
+            let format = format.or_else(|| Format::from_path(&input)).unwrap_or(Format::Srt);
+            let output = output.unwrap_or_else(|| input.with_extension(format!("synced.{}", format.extension())));
+            transcript.save(&output, format)?;
+            println!("{}", output.display());
+        }
+    }
+    Ok(())
+}
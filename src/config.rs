@@ -1,16 +1,18 @@
-use std::cmp::min;
+use std::cmp::{max, min};
 use std::fmt::Display;
 use std::fs::File;
 use std::io::{ErrorKind, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicU64, Ordering};
 
+use anyhow::anyhow;
 use once_cell::sync::Lazy;
 use reqwest::Client;
+use serde::{Deserialize, Serialize};
 
 use crate::utils::DOWNLOADING;
 
-#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, clap::ValueEnum)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, clap::ValueEnum)]
 pub enum Language {
     #[clap(name = "auto")]
     Auto,
@@ -321,6 +323,113 @@ impl From<Language> for &str {
     }
 }
 
+impl Language {
+    pub fn iso639_2(&self) -> &'static str {
+        match self {
+            Self::Auto => "und",
+            Self::English => "eng",
+            Self::Chinese => "chi",
+            Self::German => "ger",
+            Self::Spanish => "spa",
+            Self::Russian => "rus",
+            Self::Korean => "kor",
+            Self::French => "fre",
+            Self::Japanese => "jpn",
+            Self::Portuguese => "por",
+            Self::Turkish => "tur",
+            Self::Polish => "pol",
+            Self::Catalan => "cat",
+            Self::Dutch => "dut",
+            Self::Arabic => "ara",
+            Self::Swedish => "swe",
+            Self::Italian => "ita",
+            Self::Indonesian => "ind",
+            Self::Hindi => "hin",
+            Self::Finnish => "fin",
+            Self::Vietnamese => "vie",
+            Self::Hebrew => "heb",
+            Self::Ukrainian => "ukr",
+            Self::Greek => "gre",
+            Self::Malay => "may",
+            Self::Czech => "cze",
+            Self::Romanian => "rum",
+            Self::Danish => "dan",
+            Self::Hungarian => "hun",
+            Self::Tamil => "tam",
+            Self::Norwegian => "nor",
+            Self::Thai => "tha",
+            Self::Urdu => "urd",
+            Self::Croatian => "hrv",
+            Self::Bulgarian => "bul",
+            Self::Lithuanian => "lit",
+            Self::Latin => "lat",
+            Self::Maori => "mao",
+            Self::Malayalam => "mal",
+            Self::Welsh => "wel",
+            Self::Slovak => "slo",
+            Self::Telugu => "tel",
+            Self::Persian => "per",
+            Self::Latvian => "lav",
+            Self::Bengali => "ben",
+            Self::Serbian => "srp",
+            Self::Azerbaijani => "aze",
+            Self::Slovenian => "slv",
+            Self::Kannada => "kan",
+            Self::Estonian => "est",
+            Self::Macedonian => "mac",
+            Self::Breton => "bre",
+            Self::Basque => "baq",
+            Self::Icelandic => "ice",
+            Self::Armenian => "arm",
+            Self::Nepali => "nep",
+            Self::Mongolian => "mon",
+            Self::Bosnian => "bos",
+            Self::Kazakh => "kaz",
+            Self::Albanian => "alb",
+            Self::Swahili => "swa",
+            Self::Galician => "glg",
+            Self::Marathi => "mar",
+            Self::Punjabi => "pan",
+            Self::Sinhala => "sin",
+            Self::Khmer => "khm",
+            Self::Shona => "sna",
+            Self::Yoruba => "yor",
+            Self::Somali => "som",
+            Self::Afrikaans => "afr",
+            Self::Occitan => "oci",
+            Self::Georgian => "geo",
+            Self::Belarusian => "bel",
+            Self::Tajik => "tgk",
+            Self::Sindhi => "snd",
+            Self::Gujarati => "guj",
+            Self::Amharic => "amh",
+            Self::Yiddish => "yid",
+            Self::Lao => "lao",
+            Self::Uzbek => "uzb",
+            Self::Faroese => "fao",
+            Self::HaitianCreole => "hat",
+            Self::Pashto => "pus",
+            Self::Turkmen => "tuk",
+            Self::Nynorsk => "nno",
+            Self::Maltese => "mlt",
+            Self::Sanskrit => "san",
+            Self::Luxembourgish => "ltz",
+            Self::Myanmar => "bur",
+            Self::Tibetan => "tib",
+            Self::Tagalog => "tgl",
+            Self::Malagasy => "mlg",
+            Self::Assamese => "asm",
+            Self::Tatar => "tat",
+            Self::Hawaiian => "haw",
+            Self::Lingala => "lin",
+            Self::Hausa => "hau",
+            Self::Bashkir => "bak",
+            Self::Javanese => "jav",
+            Self::Sundanese => "sun",
+        }
+    }
+}
+
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, clap::ValueEnum)]
 pub enum Model {
     #[clap(name = "tiny.en")]
@@ -363,6 +472,1062 @@ impl Display for Model {
     }
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, clap::ValueEnum)]
+pub enum SubtitleMode {
+    #[clap(name = "none")]
+    None,
+    #[clap(name = "burn")]
+    Burn,
+    #[clap(name = "soft")]
+    Soft,
+}
+
+impl Display for SubtitleMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let key = match self {
+            Self::None => "无字幕",
+            Self::Burn => "烧录字幕",
+            Self::Soft => "软字幕",
+        };
+        write!(f, "{key}")
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, clap::ValueEnum)]
+pub enum Container {
+    #[clap(name = "mp4")]
+    Mp4,
+    #[clap(name = "mkv")]
+    Mkv,
+    #[clap(name = "webm")]
+    Webm,
+}
+
+impl Display for Container {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.extension())
+    }
+}
+
+impl Container {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Mp4 => "mp4",
+            Self::Mkv => "mkv",
+            Self::Webm => "webm",
+        }
+    }
+
+    pub fn subtitle_codec<P: AsRef<Path>>(&self, subtitle: P) -> &'static str {
+        match self {
+            Self::Mp4 => "mov_text",
+            Self::Mkv => match subtitle.as_ref().extension().and_then(|e| e.to_str()) {
+                Some("ass") | Some("ssa") => "ass",
+                _ => "srt",
+            },
+            Self::Webm => "webvtt",
+        }
+    }
+
+    pub fn supports(&self, video: VideoCodec, audio: AudioCodec) -> bool {
+        match self {
+            Self::Mp4 => audio != AudioCodec::Flac,
+            Self::Mkv => true,
+            Self::Webm => matches!(video, VideoCodec::Vp9 | VideoCodec::Av1) && audio == AudioCodec::Opus,
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, clap::ValueEnum)]
+pub enum VideoCodec {
+    #[clap(name = "x264")]
+    X264,
+    #[clap(name = "x265")]
+    X265,
+    #[clap(name = "vp9")]
+    Vp9,
+    #[clap(name = "av1")]
+    Av1,
+}
+
+impl Display for VideoCodec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let key = match self {
+            Self::X264 => "H.264 (x264)",
+            Self::X265 => "H.265 (x265)",
+            Self::Vp9 => "VP9",
+            Self::Av1 => "AV1 (SVT-AV1)",
+        };
+        write!(f, "{key}")
+    }
+}
+
+impl VideoCodec {
+    pub fn encoder(&self) -> &'static str {
+        match self {
+            Self::X264 => "libx264",
+            Self::X265 => "libx265",
+            Self::Vp9 => "libvpx-vp9",
+            Self::Av1 => "libsvtav1",
+        }
+    }
+
+    pub fn max_crf(&self) -> u8 {
+        match self {
+            Self::X264 | Self::X265 => 51,
+            Self::Vp9 | Self::Av1 => 63,
+        }
+    }
+
+    pub fn speed_args(&self, speed: Speed) -> Vec<String> {
+        match self {
+            Self::X264 | Self::X265 => vec!["-preset".to_string(), format!("{}", speed)],
+            Self::Vp9 => {
+                let cpu_used = match speed {
+                    Speed::Veryslow => 0,
+                    Speed::Slower | Speed::Slow => 1,
+                    Speed::Medium => 2,
+                    Speed::Fast => 3,
+                    Speed::Faster => 4,
+                    Speed::Veryfast | Speed::Superfast | Speed::Ultrafast => 5,
+                };
+                vec!["-deadline".to_string(), "good".to_string(), "-cpu-used".to_string(), cpu_used.to_string(), "-row-mt".to_string(), "1".to_string()]
+            }
+            Self::Av1 => {
+                let preset = match speed {
+                    Speed::Veryslow => 2,
+                    Speed::Slower => 3,
+                    Speed::Slow => 4,
+                    Speed::Medium => 6,
+                    Speed::Fast => 7,
+                    Speed::Faster => 8,
+                    Speed::Veryfast => 10,
+                    Speed::Superfast => 11,
+                    Speed::Ultrafast => 12,
+                };
+                vec!["-preset".to_string(), preset.to_string()]
+            }
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, clap::ValueEnum)]
+pub enum Speed {
+    #[clap(name = "ultrafast")]
+    Ultrafast,
+    #[clap(name = "superfast")]
+    Superfast,
+    #[clap(name = "veryfast")]
+    Veryfast,
+    #[clap(name = "faster")]
+    Faster,
+    #[clap(name = "fast")]
+    Fast,
+    #[clap(name = "medium")]
+    Medium,
+    #[clap(name = "slow")]
+    Slow,
+    #[clap(name = "slower")]
+    Slower,
+    #[clap(name = "veryslow")]
+    Veryslow,
+}
+
+impl Display for Speed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let key = match self {
+            Self::Ultrafast => "ultrafast",
+            Self::Superfast => "superfast",
+            Self::Veryfast => "veryfast",
+            Self::Faster => "faster",
+            Self::Fast => "fast",
+            Self::Medium => "medium",
+            Self::Slow => "slow",
+            Self::Slower => "slower",
+            Self::Veryslow => "veryslow",
+        };
+        write!(f, "{key}")
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, clap::ValueEnum)]
+pub enum AudioCodec {
+    #[clap(name = "aac")]
+    Aac,
+    #[clap(name = "opus")]
+    Opus,
+    #[clap(name = "mp3")]
+    Mp3,
+    #[clap(name = "flac")]
+    Flac,
+}
+
+impl Display for AudioCodec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let key = match self {
+            Self::Aac => "AAC",
+            Self::Opus => "Opus",
+            Self::Mp3 => "MP3",
+            Self::Flac => "FLAC",
+        };
+        write!(f, "{key}")
+    }
+}
+
+impl AudioCodec {
+    pub fn encoder(&self) -> &'static str {
+        match self {
+            Self::Aac => "aac",
+            Self::Opus => "libopus",
+            Self::Mp3 => "libmp3lame",
+            Self::Flac => "flac",
+        }
+    }
+
+    pub fn lossless(&self) -> bool {
+        *self == Self::Flac
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, clap::ValueEnum)]
+pub enum Preset {
+    #[clap(name = "youtube")]
+    YouTube,
+    #[clap(name = "small")]
+    SmallFile,
+    #[clap(name = "archive")]
+    Archive,
+    #[clap(skip)]
+    Custom,
+}
+
+impl Display for Preset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let key = match self {
+            Self::YouTube => "YouTube",
+            Self::SmallFile => "小文件",
+            Self::Archive => "存档",
+            Self::Custom => "自定义",
+        };
+        write!(f, "{key}")
+    }
+}
+
+impl Preset {
+    pub fn encoder(&self) -> Encoder {
+        match self {
+            Self::YouTube => Encoder {
+                container: Container::Mp4,
+                video_codec: VideoCodec::X264,
+                crf: 18,
+                video_bitrate: 0,
+                speed: Speed::Slow,
+                audio_codec: AudioCodec::Aac,
+                audio_bitrate: 320,
+                framerate: 30,
+            },
+            Self::SmallFile => Encoder {
+                container: Container::Mp4,
+                video_codec: VideoCodec::X265,
+                crf: 30,
+                video_bitrate: 0,
+                speed: Speed::Medium,
+                audio_codec: AudioCodec::Aac,
+                audio_bitrate: 96,
+                framerate: 24,
+            },
+            Self::Archive => Encoder {
+                container: Container::Mkv,
+                video_codec: VideoCodec::X265,
+                crf: 16,
+                video_bitrate: 0,
+                speed: Speed::Slower,
+                audio_codec: AudioCodec::Flac,
+                audio_bitrate: 0,
+                framerate: 30,
+            },
+            Self::Custom => Encoder::default(),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub struct Encoder {
+    pub container: Container,
+    pub video_codec: VideoCodec,
+    pub crf: u8,
+    pub video_bitrate: u32,
+    pub speed: Speed,
+    pub audio_codec: AudioCodec,
+    pub audio_bitrate: u32,
+    pub framerate: u32,
+}
+
+impl Default for Encoder {
+    fn default() -> Self {
+        Self {
+            container: Container::Mp4,
+            video_codec: VideoCodec::X264,
+            crf: 23,
+            video_bitrate: 0,
+            speed: Speed::Medium,
+            audio_codec: AudioCodec::Aac,
+            audio_bitrate: 128,
+            framerate: 30,
+        }
+    }
+}
+
+impl Encoder {
+    pub fn check(&self) -> anyhow::Result<()> {
+        if !self.container.supports(self.video_codec, self.audio_codec) {
+            return Err(anyhow!("{} cannot contain {} + {}", self.container, self.video_codec, self.audio_codec));
+        }
+        if self.crf > self.video_codec.max_crf() {
+            return Err(anyhow!("crf of {} must be at most {}", self.video_codec, self.video_codec.max_crf()));
+        }
+        if self.framerate == 0 {
+            return Err(anyhow!("invalid framerate"));
+        }
+        Ok(())
+    }
+
+    pub fn args(&self, still: bool) -> Vec<String> {
+        let mut args = vec!["-c:v".to_string(), self.video_codec.encoder().to_string()];
+        args.extend(self.video_codec.speed_args(self.speed));
+        if still && self.video_codec == VideoCodec::X264 {
+            args.extend(["-tune".to_string(), "stillimage".to_string()]);
+        }
+        if self.video_bitrate > 0 {
+            args.extend(["-b:v".to_string(), format!("{}k", self.video_bitrate)]);
+        } else {
+            args.extend(["-crf".to_string(), self.crf.to_string()]);
+            if self.video_codec == VideoCodec::Vp9 {
+                args.extend(["-b:v".to_string(), "0".to_string()]);
+            }
+        }
+        args.extend(["-c:a".to_string(), self.audio_codec.encoder().to_string()]);
+        if !self.audio_codec.lossless() && self.audio_bitrate > 0 {
+            args.extend(["-b:a".to_string(), format!("{}k", self.audio_bitrate)]);
+        }
+        args.extend(["-pix_fmt".to_string(), "yuv420p".to_string()]);
+        if still {
+            args.extend([
+                "-force_key_frames".to_string(),
+                "expr:gte(t,n_forced*10)".to_string(),
+                "-fps_mode".to_string(),
+                "vfr".to_string(),
+            ]);
+        } else {
+            args.extend(["-r".to_string(), self.framerate.to_string()]);
+        }
+        args
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, clap::ValueEnum)]
+pub enum Resolution {
+    #[clap(name = "source")]
+    Source,
+    #[clap(name = "720p")]
+    Hd720,
+    #[clap(name = "1080p")]
+    Hd1080,
+    #[clap(name = "4k")]
+    Uhd4k,
+    #[clap(name = "custom")]
+    Custom,
+}
+
+impl Display for Resolution {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let key = match self {
+            Self::Source => "原图",
+            Self::Hd720 => "720p",
+            Self::Hd1080 => "1080p",
+            Self::Uhd4k => "4K",
+            Self::Custom => "自定义",
+        };
+        write!(f, "{key}")
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, clap::ValueEnum)]
+pub enum Aspect {
+    #[clap(name = "16:9")]
+    Landscape,
+    #[clap(name = "9:16")]
+    Portrait,
+    #[clap(name = "1:1")]
+    Square,
+    #[clap(name = "4:5")]
+    Vertical,
+}
+
+impl Display for Aspect {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (width, height) = self.ratio();
+        write!(f, "{width}:{height}")
+    }
+}
+
+impl Aspect {
+    pub fn ratio(&self) -> (u32, u32) {
+        match self {
+            Self::Landscape => (16, 9),
+            Self::Portrait => (9, 16),
+            Self::Square => (1, 1),
+            Self::Vertical => (4, 5),
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, clap::ValueEnum)]
+pub enum Fill {
+    #[clap(name = "color")]
+    Color,
+    #[clap(name = "blur")]
+    Blur,
+    #[clap(name = "crop")]
+    Crop,
+}
+
+impl Display for Fill {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let key = match self {
+            Self::Color => "纯色",
+            Self::Blur => "模糊",
+            Self::Crop => "裁剪",
+        };
+        write!(f, "{key}")
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub struct Canvas {
+    pub resolution: Resolution,
+    pub aspect: Aspect,
+    pub width: u32,
+    pub height: u32,
+    pub fill: Fill,
+    pub color: [u8; 3],
+}
+
+impl Default for Canvas {
+    fn default() -> Self {
+        Self {
+            resolution: Resolution::Hd1080,
+            aspect: Aspect::Landscape,
+            width: 1920,
+            height: 1080,
+            fill: Fill::Color,
+            color: [0, 0, 0],
+        }
+    }
+}
+
+impl Canvas {
+    pub fn size(&self, (width, height): (u32, u32)) -> (u32, u32) {
+        let short = match self.resolution {
+            Resolution::Hd720 => 720,
+            Resolution::Hd1080 => 1080,
+            Resolution::Uhd4k => 2160,
+            Resolution::Source | Resolution::Custom => 0,
+        };
+        let (a, b) = self.aspect.ratio();
+        let (width, height) = match self.resolution {
+            Resolution::Source => (width, height),
+            Resolution::Custom => (self.width, self.height),
+            _ if a >= b => (short * a / b, short),
+            _ => (short, short * b / a),
+        };
+        (max(width / 2 * 2, 2), max(height / 2 * 2, 2))
+    }
+
+    pub fn subtitle_style(&self, (width, height): (u32, u32)) -> Option<(u32, u32)> {
+        let ratio = width as f32 / height as f32;
+        if ratio >= 1.5 {
+            None
+        } else if ratio >= 1.0 {
+            Some((14, 20))
+        } else if ratio >= 0.7 {
+            Some((12, 30))
+        } else {
+            Some((10, 55))
+        }
+    }
+
+    pub fn filter(&self, input: &str, id: usize, (width, height): (u32, u32)) -> String {
+        let fit = format!("scale={width}:{height}:force_original_aspect_ratio=decrease:force_divisible_by=2");
+        match self.fill {
+            Fill::Color => format!(
+                "[{input}]{fit},pad={width}:{height}:(ow-iw)/2:(oh-ih)/2:color=0x{:02x}{:02x}{:02x},setsar=1",
+                self.color[0],
+                self.color[1],
+                self.color[2],
+            ),
+            Fill::Crop => format!("[{input}]scale={width}:{height}:force_original_aspect_ratio=increase,crop={width}:{height},setsar=1"),
+            Fill::Blur => format!(
+                "[{input}]split[canvas_fg{id}][canvas_bg{id}];\
+                [canvas_bg{id}]scale={width}:{height}:force_original_aspect_ratio=increase,crop={width}:{height},boxblur=20:2[bg{id}];\
+                [canvas_fg{id}]{fit}[fg{id}];\
+                [bg{id}][fg{id}]overlay=(W-w)/2:(H-h)/2,setsar=1"
+            ),
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, clap::ValueEnum)]
+pub enum SlideTiming {
+    #[clap(name = "even")]
+    Even,
+    #[clap(name = "manual")]
+    Manual,
+    #[clap(name = "cues")]
+    Cues,
+}
+
+impl Display for SlideTiming {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let key = match self {
+            Self::Even => "平均分配",
+            Self::Manual => "指定时间",
+            Self::Cues => "跟随字幕",
+        };
+        write!(f, "{key}")
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub struct Slideshow {
+    pub timing: SlideTiming,
+    pub crossfade: f64,
+}
+
+impl Default for Slideshow {
+    fn default() -> Self {
+        Self {
+            timing: SlideTiming::Even,
+            crossfade: 0.0,
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, clap::ValueEnum)]
+pub enum Motion {
+    #[clap(name = "none")]
+    None,
+    #[clap(name = "zoom-in")]
+    ZoomIn,
+    #[clap(name = "zoom-out")]
+    ZoomOut,
+    #[clap(name = "pan-left")]
+    PanLeft,
+    #[clap(name = "pan-right")]
+    PanRight,
+    #[clap(name = "pan-up")]
+    PanUp,
+    #[clap(name = "pan-down")]
+    PanDown,
+}
+
+impl Display for Motion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let key = match self {
+            Self::None => "无",
+            Self::ZoomIn => "放大",
+            Self::ZoomOut => "缩小",
+            Self::PanLeft => "向左平移",
+            Self::PanRight => "向右平移",
+            Self::PanUp => "向上平移",
+            Self::PanDown => "向下平移",
+        };
+        write!(f, "{key}")
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub struct KenBurns {
+    pub motion: Motion,
+    pub amount: f64,
+    pub period: f64,
+}
+
+impl Default for KenBurns {
+    fn default() -> Self {
+        Self {
+            motion: Motion::None,
+            amount: 1.2,
+            period: 20.0,
+        }
+    }
+}
+
+impl KenBurns {
+    pub fn filter(&self, (width, height): (u32, u32), framerate: u32) -> Option<String> {
+        let progress = format!("(1-cos(2*PI*on/{:.3}))/2", self.period * framerate as f64);
+        let amount = self.amount;
+        let (zoom, x, y) = match self.motion {
+            Motion::None => return None,
+            Motion::ZoomIn => (format!("1+{:.3}*{progress}", amount - 1.0), "iw/2-iw/zoom/2".to_string(), "ih/2-ih/zoom/2".to_string()),
+            Motion::ZoomOut => (format!("{amount:.3}-{:.3}*{progress}", amount - 1.0), "iw/2-iw/zoom/2".to_string(), "ih/2-ih/zoom/2".to_string()),
+            Motion::PanLeft => (format!("{amount:.3}"), format!("(iw-iw/zoom)*(1-{progress})"), "ih/2-ih/zoom/2".to_string()),
+            Motion::PanRight => (format!("{amount:.3}"), format!("(iw-iw/zoom)*{progress}"), "ih/2-ih/zoom/2".to_string()),
+            Motion::PanUp => (format!("{amount:.3}"), "iw/2-iw/zoom/2".to_string(), format!("(ih-ih/zoom)*(1-{progress})")),
+            Motion::PanDown => (format!("{amount:.3}"), "iw/2-iw/zoom/2".to_string(), format!("(ih-ih/zoom)*{progress}")),
+        };
+        Some(format!(
+            "scale={}:{},zoompan=z='{zoom}':x='{x}':y='{y}':d=1:s={width}x{height}:fps={framerate},setsar=1",
+            width * 2,
+            height * 2,
+        ))
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, clap::ValueEnum, Serialize, Deserialize)]
+pub enum Position {
+    #[clap(name = "top-left")]
+    TopLeft,
+    #[clap(name = "top")]
+    Top,
+    #[clap(name = "top-right")]
+    TopRight,
+    #[clap(name = "center")]
+    Center,
+    #[clap(name = "bottom-left")]
+    BottomLeft,
+    #[clap(name = "bottom")]
+    Bottom,
+    #[clap(name = "bottom-right")]
+    BottomRight,
+}
+
+impl Display for Position {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let key = match self {
+            Self::TopLeft => "左上",
+            Self::Top => "上方",
+            Self::TopRight => "右上",
+            Self::Center => "居中",
+            Self::BottomLeft => "左下",
+            Self::Bottom => "下方",
+            Self::BottomRight => "右下",
+        };
+        write!(f, "{key}")
+    }
+}
+
+impl Position {
+    pub fn overlay(&self, margin: u32) -> String {
+        let (x, y) = match self {
+            Self::TopLeft => (format!("{margin}"), format!("{margin}")),
+            Self::Top => ("(W-w)/2".to_string(), format!("{margin}")),
+            Self::TopRight => (format!("W-w-{margin}"), format!("{margin}")),
+            Self::Center => ("(W-w)/2".to_string(), "(H-h)/2".to_string()),
+            Self::BottomLeft => (format!("{margin}"), format!("H-h-{margin}")),
+            Self::Bottom => ("(W-w)/2".to_string(), format!("H-h-{margin}")),
+            Self::BottomRight => (format!("W-w-{margin}"), format!("H-h-{margin}")),
+        };
+        format!("x={x}:y={y}")
+    }
+
+    pub fn alignment(&self) -> u8 {
+        match self {
+            Self::TopLeft => 7,
+            Self::Top => 8,
+            Self::TopRight => 9,
+            Self::Center => 5,
+            Self::BottomLeft => 1,
+            Self::Bottom => 2,
+            Self::BottomRight => 3,
+        }
+    }
+
+    pub fn drawtext(&self) -> (String, String) {
+        let (x, y) = match self {
+            Self::TopLeft => ("h/30", "h/30"),
+            Self::Top => ("(w-text_w)/2", "h/30"),
+            Self::TopRight => ("w-text_w-h/30", "h/30"),
+            Self::Center => ("(w-text_w)/2", "(h-text_h)/2"),
+            Self::BottomLeft => ("h/30", "h-text_h-h/30"),
+            Self::Bottom => ("(w-text_w)/2", "h-text_h-h/30"),
+            Self::BottomRight => ("w-text_w-h/30", "h-text_h-h/30"),
+        };
+        (x.to_string(), y.to_string())
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, clap::ValueEnum)]
+pub enum Visualizer {
+    #[clap(name = "none")]
+    None,
+    #[clap(name = "waves")]
+    Waves,
+    #[clap(name = "spectrum")]
+    Spectrum,
+    #[clap(name = "bars")]
+    Bars,
+    #[clap(name = "vectorscope")]
+    Vectorscope,
+}
+
+impl Display for Visualizer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let key = match self {
+            Self::None => "无",
+            Self::Waves => "波形",
+            Self::Spectrum => "频谱图",
+            Self::Bars => "频谱柱",
+            Self::Vectorscope => "矢量示波器",
+        };
+        write!(f, "{key}")
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub struct Visualization {
+    pub kind: Visualizer,
+    pub position: Position,
+    pub width: u32,
+    pub height: u32,
+    pub color: [u8; 3],
+    pub opacity: f32,
+}
+
+impl Default for Visualization {
+    fn default() -> Self {
+        Self {
+            kind: Visualizer::None,
+            position: Position::Bottom,
+            width: 100,
+            height: 20,
+            color: [255, 255, 255],
+            opacity: 0.8,
+        }
+    }
+}
+
+impl Visualization {
+    pub fn filter(&self, input: &str, (width, height): (u32, u32), framerate: u32) -> Option<String> {
+        let size = format!(
+            "{}x{}",
+            max(width * self.width / 100 / 2 * 2, 2),
+            max(height * self.height / 100 / 2 * 2, 2),
+        );
+        let [r, g, b] = self.color;
+        let color = format!("0x{:02x}{:02x}{:02x}", r, g, b);
+        let source = match self.kind {
+            Visualizer::None => return None,
+            Visualizer::Waves => format!("showwaves=s={size}:mode=cline:colors={color}:rate={framerate}"),
+            Visualizer::Spectrum => format!("showspectrum=s={size}:mode=combined:color=intensity:slide=scroll:fps={framerate},format=gray"),
+            Visualizer::Bars => format!("showfreqs=s={size}:mode=bar:ascale=log:fscale=log:colors={color}:rate={framerate}"),
+            Visualizer::Vectorscope => format!("avectorscope=s={size}:draw=line:rc={r}:gc={g}:bc={b}:rate={framerate}"),
+        };
+        let tint = match self.kind {
+            Visualizer::Spectrum => format!(":rr={:.3}:gg={:.3}:bb={:.3}", r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0),
+            _ => String::new(),
+        };
+        Some(format!("[{input}]{source},format=rgba,colorchannelmixer=aa={:.2}{tint}", self.opacity))
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, clap::ValueEnum)]
+pub enum Background {
+    #[clap(name = "color")]
+    Color,
+    #[clap(name = "gradient")]
+    Gradient,
+    #[clap(name = "cover")]
+    Cover,
+}
+
+impl Display for Background {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let key = match self {
+            Self::Color => "纯色",
+            Self::Gradient => "渐变",
+            Self::Cover => "专辑封面",
+        };
+        write!(f, "{key}")
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub struct GeneratedBackground {
+    pub kind: Background,
+    pub color: [u8; 3],
+    pub color2: [u8; 3],
+    pub text: bool,
+    pub text_color: [u8; 3],
+}
+
+impl Default for GeneratedBackground {
+    fn default() -> Self {
+        Self {
+            kind: Background::Color,
+            color: [0x20, 0x20, 0x28],
+            color2: [0x40, 0x30, 0x60],
+            text: false,
+            text_color: [255, 255, 255],
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct TitleCard {
+    pub enabled: bool,
+    pub text: String,
+    pub font: String,
+    pub size: u32,
+    pub color: [u8; 3],
+    pub position: Position,
+    pub start: f64,
+    pub end: f64,
+}
+
+impl Default for TitleCard {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            text: String::new(),
+            font: "Microsoft YaHei UI".to_string(),
+            size: 72,
+            color: [255, 255, 255],
+            position: Position::Center,
+            start: 0.0,
+            end: 5.0,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct Watermark {
+    pub enabled: bool,
+    pub text: String,
+    pub font: String,
+    pub size: u32,
+    pub color: [u8; 3],
+    pub opacity: f32,
+    pub position: Position,
+}
+
+impl Default for Watermark {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            text: String::new(),
+            font: "Microsoft YaHei UI".to_string(),
+            size: 28,
+            color: [255, 255, 255],
+            opacity: 0.5,
+            position: Position::BottomRight,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct Logo {
+    pub position: Position,
+    pub scale: u32,
+    pub opacity: f32,
+}
+
+impl Default for Logo {
+    fn default() -> Self {
+        Self {
+            position: Position::TopRight,
+            scale: 10,
+            opacity: 0.8,
+        }
+    }
+}
+
+impl Logo {
+    pub fn filter(&self, input: &str, size: (u32, u32)) -> String {
+        format!(
+            "[{}]scale={}:-1,format=rgba,colorchannelmixer=aa={:.2}",
+            input,
+            (size.0 * self.scale / 100).max(2),
+            self.opacity,
+        )
+    }
+}
+
+#[derive(Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct Overlay {
+    pub title: TitleCard,
+    pub watermark: Watermark,
+    pub logo: Logo,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Template {
+    pub overlay: Overlay,
+    pub logo: Option<PathBuf>,
+}
+
+impl Template {
+    fn dir() -> anyhow::Result<PathBuf> {
+        Ok(std::env::current_dir()?.join("templates"))
+    }
+
+    fn path(name: &str) -> anyhow::Result<PathBuf> {
+        if name.is_empty() || name.contains(['/', '\\', '.']) {
+            return Err(anyhow!("invalid template name"));
+        }
+        Ok(Self::dir()?.join(format!("{}.json", name)))
+    }
+
+    pub fn list() -> Vec<String> {
+        let mut names = Self::dir()
+            .and_then(|dir| Ok(std::fs::read_dir(dir)?))
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter_map(|e| {
+                let path = e.path();
+                (path.extension()? == "json").then(|| path.file_stem()?.to_str().map(String::from))?
+            })
+            .collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    pub fn load(name: &str) -> anyhow::Result<Self> {
+        let path = Self::path(name)?;
+        let json = std::fs::read_to_string(&path).map_err(|_| anyhow!("template not found: {}", name))?;
+        Ok(serde_json::from_str(&json)?)
+    }
+
+    pub fn save(&self, name: &str) -> anyhow::Result<()> {
+        let path = Self::path(name)?;
+        std::fs::create_dir_all(Self::dir()?)?;
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub struct SubtitleStyle {
+    pub enabled: bool,
+    pub font: String,
+    pub size: u32,
+    pub color: [u8; 3],
+    pub outline_color: [u8; 3],
+    pub outline: f32,
+    pub shadow: f32,
+    pub margin: u32,
+    pub alignment: Position,
+    pub bold: bool,
+}
+
+impl Default for SubtitleStyle {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            font: "Microsoft YaHei UI".to_string(),
+            size: 20,
+            color: [255, 255, 255],
+            outline_color: [0, 0, 0],
+            outline: 2.0,
+            shadow: 1.0,
+            margin: 20,
+            alignment: Position::Bottom,
+            bold: false,
+        }
+    }
+}
+
+impl SubtitleStyle {
+    pub fn force_style(&self) -> String {
+        let colour = |[r, g, b]: [u8; 3]| format!("&H00{:02X}{:02X}{:02X}", b, g, r);
+        format!(
+            "FontName={},Fontsize={},PrimaryColour={},OutlineColour={},BorderStyle=1,Outline={},Shadow={},MarginV={},Alignment={},Bold={}",
+            self.font,
+            self.size,
+            colour(self.color),
+            colour(self.outline_color),
+            self.outline,
+            self.shadow,
+            self.margin,
+            self.alignment.alignment(),
+            if self.bold { -1 } else { 0 },
+        )
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub struct Segmentation {
+    pub enabled: bool,
+    pub chars_per_line: usize,
+    pub lines: usize,
+    pub duration: f64,
+    pub cps: f64,
+}
+
+impl Default for Segmentation {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            chars_per_line: 42,
+            lines: 2,
+            duration: 7.0,
+            cps: 20.0,
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, clap::ValueEnum)]
+pub enum Hallucination {
+    Keep,
+    Flag,
+    Drop,
+}
+
+impl Display for Hallucination {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let key = match self {
+            Self::Keep => "保留",
+            Self::Flag => "标记",
+            Self::Drop => "删除",
+        };
+        write!(f, "{key}")
+    }
+}
+
+#[derive(Clone)]
+pub struct MergeConfig {
+    pub preset: Preset,
+    pub encoder: Encoder,
+    pub still: bool,
+    pub canvas: Canvas,
+    pub slideshow: Slideshow,
+    pub ken_burns: KenBurns,
+    pub visualization: Visualization,
+    pub generated: GeneratedBackground,
+    pub overlay: Overlay,
+    pub subtitle_style: SubtitleStyle,
+    pub excerpt: Option<(f64, f64)>,
+}
+
+impl Default for MergeConfig {
+    fn default() -> Self {
+        Self {
+            preset: Preset::Custom,
+            encoder: Encoder::default(),
+            still: false,
+            canvas: Canvas::default(),
+            slideshow: Slideshow::default(),
+            ken_burns: KenBurns::default(),
+            visualization: Visualization::default(),
+            generated: GeneratedBackground::default(),
+            overlay: Overlay::default(),
+            subtitle_style: SubtitleStyle::default(),
+            excerpt: None,
+        }
+    }
+}
+
 pub static FILE_SIZE: AtomicU64 = AtomicU64::new(!0);
 pub static DOWNLOADED: AtomicU64 = AtomicU64::new(0);
 pub static CLIENT: Lazy<Client> = Lazy::new(|| Client::new());
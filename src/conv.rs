@@ -1,34 +1,87 @@
-use std::path::{Path, PathBuf};
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 use std::sync::atomic::Ordering;
 
 use eframe::CreationContext;
 use egui::FontFamily::Proportional;
-use egui::FontId;
+use egui::{ColorImage, FontId, TextureHandle};
 use egui::TextStyle::{Body, Button, Heading, Monospace, Name, Small};
 
-use crate::config::{Language, Model};
+use crate::align::align_text;
+use crate::config::{Hallucination, Language, MergeConfig, Model, Segmentation, SubtitleMode, Template};
+use crate::editor::Editor;
 use crate::font::load_fonts;
-use crate::utils::{MERGE, merge, WHISPER};
-use crate::whisper::{Format, Whisper};
+use crate::utils;
+use crate::utils::{frame, MERGE, merge_files, PREVIEW, WHISPER};
+use crate::whisper::{Format, Transcript, Whisper};
 
 #[derive(Clone)]
 pub struct Conv {
     pub files: Arc<Mutex<Files>>,
     pub config: Config,
+    pub template: String,
+    pub templates: Vec<String>,
+    pub template_status: String,
+    pub whisper_status: Arc<Mutex<String>>,
+    pub merge_status: Arc<Mutex<String>>,
+    pub preview: Arc<Mutex<Preview>>,
+    pub editor: Editor,
 }
 
 #[derive(Clone)]
 pub struct Config {
     pub lang: Language,
     pub model: Model,
+    pub bilingual: bool,
+    pub hallucination: Hallucination,
+    pub vad: bool,
+    pub segmentation: Segmentation,
+    pub merge: MergeConfig,
+}
+
+pub struct Preview {
+    pub time: f64,
+    pub duration: f64,
+    pub length: f64,
+    pub transcript: Option<(PathBuf, Transcript)>,
+    pub image: Option<ColorImage>,
+    pub texture: Option<TextureHandle>,
+    pub output: Option<PathBuf>,
+}
+
+impl Default for Preview {
+    fn default() -> Self {
+        Self {
+            time: 0.0,
+            duration: 0.0,
+            length: 15.0,
+            transcript: None,
+            image: None,
+            texture: None,
+            output: None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Default)]
 pub struct Files {
     pub audio: Option<PathBuf>,
-    pub image: Option<PathBuf>,
-    pub subtitle: Option<PathBuf>,
+    pub images: Vec<Slide>,
+    pub subtitles: Vec<Subtitle>,
+    pub logo: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Slide {
+    pub path: PathBuf,
+    pub start: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct Subtitle {
+    pub path: PathBuf,
+    pub lang: Language,
+    pub mode: SubtitleMode,
 }
 
 impl Conv {
@@ -49,7 +102,22 @@ impl Conv {
 
         Box::new(Self {
             files: Default::default(),
-            config: Config { lang: Language::Auto, model: Model::Medium },
+            config: Config {
+                lang: Language::Auto,
+                model: Model::Medium,
+                bilingual: false,
+                hallucination: Hallucination::Keep,
+                vad: false,
+                segmentation: Segmentation::default(),
+                merge: MergeConfig::default(),
+            },
+            template: String::new(),
+            templates: Template::list(),
+            template_status: String::new(),
+            whisper_status: Default::default(),
+            merge_status: Default::default(),
+            preview: Default::default(),
+            editor: Editor::default(),
         })
     }
 
@@ -66,19 +134,40 @@ impl Conv {
     pub fn open_image(&self, files: Arc<Mutex<Files>>) {
         tokio::spawn(async move {
             if let Some(path) = rfd::FileDialog::new()
-                .add_filter("Image File", &["jpg", "png"])
+                .add_filter("Image File", &["jpg", "png", "gif", "webp"])
+                .add_filter("Video File", &["mp4", "mkv", "mov", "webm"])
+                .pick_files() {
+                files.lock().unwrap().images = path
+                    .into_iter()
+                    .map(|path| Slide { path, start: 0.0 })
+                    .collect();
+            }
+        });
+    }
+
+    pub fn open_logo(&self, files: Arc<Mutex<Files>>) {
+        tokio::spawn(async move {
+            if let Some(path) = rfd::FileDialog::new()
+                .add_filter("Image File", &["png", "jpg", "webp", "gif"])
                 .pick_file() {
-                files.lock().unwrap().image = Some(path);
+                files.lock().unwrap().logo = Some(path);
             }
         });
     }
 
     pub fn open_subtitle(&self, files: Arc<Mutex<Files>>) {
+        let lang = self.config.lang;
         tokio::spawn(async move {
             if let Some(path) = rfd::FileDialog::new()
-                .add_filter("Subtitle File", &["srt", "lrc", "vtt"])
+                .add_filter("Subtitle File", &["srt", "lrc", "vtt", "ass"])
                 .pick_file() {
-                files.lock().unwrap().subtitle = Some(path);
+                let mut files = files.lock().unwrap();
+                let mode = if files.subtitles.iter().any(|s| s.mode == SubtitleMode::Burn) {
+                    SubtitleMode::Soft
+                } else {
+                    SubtitleMode::Burn
+                };
+                files.subtitles.push(Subtitle { path, lang, mode });
             }
         });
     }
@@ -88,15 +177,32 @@ impl Conv {
         let audio = file.audio.clone();
         let model = self.config.model;
         let lang = self.config.lang;
+        let bilingual = self.config.bilingual;
+        let hallucination = self.config.hallucination;
+        let vad = self.config.vad;
+        let segmentation = self.config.segmentation.clone();
+        let status = self.whisper_status.clone();
         tokio::spawn(async move {
             if let Some(ref audio) = audio {
                 if let Ok(ref mut w) = Whisper::new(lang, model).await {
                     WHISPER.store(true, Ordering::Relaxed);
-                    if let Ok(ref t) = w.transcribe(audio, false, false) {
-                        t.write_file(audio, Format::Lrc);
-                        t.write_file(audio, Format::Srt);
-                        t.write_file(audio, Format::Vtt);
-                    }
+                    w.hallucination = hallucination;
+                    w.vad = vad;
+                    let result = w.transcribe(audio, false, segmentation.enabled).and_then(|t| {
+                        let t = match segmentation.enabled {
+                            true => t.resegment(&segmentation),
+                            false => t,
+                        };
+                        t.write_file(audio, Format::Lrc)?;
+                        t.write_file(audio, Format::Srt)?;
+                        t.write_file(audio, Format::Vtt)?;
+                        if bilingual {
+                            let translation = w.transcribe(audio, true, false)?;
+                            t.stack(&translation).write_bilingual(audio)?;
+                        }
+                        Ok(())
+                    });
+                    *status.lock().unwrap() = result.err().map(|e| e.to_string()).unwrap_or_default();
                 }
             }
 
@@ -104,45 +210,91 @@ impl Conv {
         });
     }
 
-    pub fn ffmpeg_merge(&self) {
-        let file = self.files.lock().unwrap();
-        let image = file.image.clone();
-        let audio = file.audio.clone();
-        let subtitle = file.subtitle.clone();
+    pub fn align_text(&self) {
+        let audio = self.files.lock().unwrap().audio.clone();
+        let model = self.config.model;
+        let lang = self.config.lang;
+        let status = self.whisper_status.clone();
         tokio::spawn(async move {
-            MERGE.store(true, Ordering::Relaxed);
-            if let (Some(ref image), Some(ref audio), Some(ref subtitle)) = (image, audio, subtitle) {
-                let current = std::env::current_dir().unwrap();
-                let subtitle_cache = Path::new(&uuid::Uuid::new_v4().to_string()).with_extension(subtitle.extension().unwrap());
-                if !current.join(&subtitle_cache).exists() {
-                    std::fs::copy(subtitle, current.join(&subtitle_cache)).unwrap();
-                }
-                let output = audio.with_extension("mp4");
-
-                if let Ok(child) = merge(
-                    audio.to_str().unwrap(),
-                    image.to_str().unwrap(),
-                    subtitle_cache.to_str().unwrap(),
-                    output.to_str().unwrap(),
-                ).as_mut() {
-                    if child.wait().is_err() {
-                        MERGE.store(false, Ordering::Relaxed);
-                        return;
-                    }
-                } else {
-                    MERGE.store(false, Ordering::Relaxed);
-                    return;
-                }
-                if std::fs::remove_file(current.join(subtitle_cache)).is_err() {
-                    MERGE.store(false, Ordering::Relaxed);
-                    return;
-                }
-            } else {
-                MERGE.store(false, Ordering::Relaxed);
+            let Some(ref audio) = audio else {
+                return;
+            };
+            let Some(text) = rfd::FileDialog::new()
+                .add_filter("Text File", &["txt", "lrc"])
+                .pick_file()
+                .and_then(|path| std::fs::read_to_string(path).ok()) else {
                 return;
+            };
+            if let Ok(ref mut w) = Whisper::new(lang, model).await {
+                WHISPER.store(true, Ordering::Relaxed);
+                let result = w.transcribe(audio, false, true).and_then(|t| {
+                    let t = align_text(&text, &t.word_utterances.unwrap_or_default());
+                    t.write_file(audio, Format::Lrc)?;
+                    t.write_file(audio, Format::Srt)?;
+                    Ok(())
+                });
+                *status.lock().unwrap() = result.err().map(|e| e.to_string()).unwrap_or_default();
             }
+            WHISPER.store(false, Ordering::Relaxed);
+        });
+    }
 
+    pub fn ffmpeg_merge(&self) {
+        let files = self.files.lock().unwrap().clone();
+        let config = self.config.merge.clone();
+        let status = self.merge_status.clone();
+        tokio::spawn(async move {
+            MERGE.store(true, Ordering::Relaxed);
+            let result = merge_files(&files, &config, None);
+            *status.lock().unwrap() = result.err().map(|e| e.to_string()).unwrap_or_default();
             MERGE.store(false, Ordering::Relaxed);
         });
     }
+
+    pub fn render_excerpt(&self) {
+        let files = self.files.lock().unwrap().clone();
+        let preview = self.preview.clone();
+        let excerpt = {
+            let preview = preview.lock().unwrap();
+            (preview.time, preview.length)
+        };
+        let config = MergeConfig { excerpt: Some(excerpt), ..self.config.merge.clone() };
+        let status = self.merge_status.clone();
+        tokio::spawn(async move {
+            MERGE.store(true, Ordering::Relaxed);
+            let result = merge_files(&files, &config, None);
+            *status.lock().unwrap() = result.as_ref().err().map(|e| e.to_string()).unwrap_or_default();
+            preview.lock().unwrap().output = result.ok();
+            MERGE.store(false, Ordering::Relaxed);
+        });
+    }
+
+    pub fn render_preview(&self) {
+        let files = self.files.lock().unwrap().clone();
+        let config = self.config.merge.clone();
+        let preview = self.preview.clone();
+        tokio::spawn(async move {
+            PREVIEW.store(true, Ordering::Relaxed);
+            let time = preview.lock().unwrap().time;
+            let duration = files.audio.as_ref().and_then(|audio| utils::duration(audio).ok());
+            let transcript = files.subtitles
+                .iter()
+                .find(|s| s.mode == SubtitleMode::Burn)
+                .and_then(|s| Some((s.path.clone(), Transcript::read_file(&s.path).ok()?)));
+            let image = frame(&files, &config, time).ok().and_then(|path| {
+                let image = image::open(&path).ok().map(|i| i.to_rgba8());
+                std::fs::remove_file(path).ok();
+                image
+            });
+
+            let mut preview = preview.lock().unwrap();
+            preview.duration = duration.unwrap_or_default();
+            preview.transcript = transcript;
+            if let Some(image) = image {
+                let size = [image.width() as usize, image.height() as usize];
+                preview.image = Some(ColorImage::from_rgba_unmultiplied(size, image.as_raw()));
+            }
+            PREVIEW.store(false, Ordering::Relaxed);
+        });
+    }
 }
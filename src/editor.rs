@@ -0,0 +1,471 @@
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use egui::{Color32, Context, DragValue, Id, Pos2, Rect, Sense, Stroke, Ui, Vec2, ViewportBuilder, ViewportId};
+
+use crate::align::{auto_sync, Correction};
+use crate::check;
+use crate::check::Issue;
+use crate::config::Segmentation;
+use crate::ui::select;
+use crate::utils;
+use crate::whisper::{Format, is_cjk, parse_time, Transcript, Utterance};
+
+#[derive(Clone)]
+pub struct Editor {
+    pub open: bool,
+    pub path: Option<PathBuf>,
+    pub utterances: Vec<Utterance>,
+    pub format: Format,
+    pub status: String,
+    pub audio: Option<PathBuf>,
+    pub waveform: Arc<Mutex<Vec<f32>>>,
+    pub drift: bool,
+    pub correction: Arc<Mutex<Option<anyhow::Result<Correction>>>>,
+    pub issues: Vec<Issue>,
+    pub checking: bool,
+    pub limits: Segmentation,
+    pub length: Option<i64>,
+    pub zoom: f32,
+    pub offset: f64,
+    pub sync: [(i64, i64); 2],
+    pub framerate: (f64, f64),
+    editing: bool,
+    undo: Vec<Vec<Utterance>>,
+    redo: Vec<Vec<Utterance>>,
+}
+
+impl Default for Editor {
+    fn default() -> Self {
+        Self {
+            open: false,
+            path: None,
+            utterances: vec![],
+            format: Format::Srt,
+            status: String::new(),
+            audio: None,
+            waveform: Default::default(),
+            drift: false,
+            correction: Default::default(),
+            issues: vec![],
+            checking: false,
+            limits: Segmentation::default(),
+            length: None,
+            zoom: 50.0,
+            offset: 0.0,
+            sync: [(0, 0); 2],
+            framerate: (23.976, 25.0),
+            editing: false,
+            undo: vec![],
+            redo: vec![],
+        }
+    }
+}
+
+impl Editor {
+    pub fn load(&mut self, path: PathBuf, audio: Option<PathBuf>) -> anyhow::Result<()> {
+        let transcript = Transcript::read_file(&path)?;
+        self.waveform.lock().unwrap().clear();
+        self.length = audio.as_ref().and_then(|audio| utils::duration(audio).ok()).map(|d| (d * 100.0) as i64);
+        self.audio = audio.clone();
+        if let Some(audio) = audio {
+            let waveform = self.waveform.clone();
+            tokio::spawn(async move {
+                if let Ok(samples) = utils::read_file(audio) {
+                    *waveform.lock().unwrap() = samples
+                        .chunks(160)
+                        .map(|c| c.iter().fold(0.0f32, |peak, s| peak.max(s.abs())))
+                        .collect();
+                }
+            });
+        }
+        self.utterances = transcript.utterances;
+        self.format = Format::from_path(&path).unwrap_or(Format::Srt);
+        self.path = Some(path);
+        self.status.clear();
+        self.issues.clear();
+        self.checking = false;
+        self.undo.clear();
+        self.redo.clear();
+        self.open = true;
+        Ok(())
+    }
+
+    pub fn save(&self) -> anyhow::Result<PathBuf> {
+        let path = self.path.as_ref().ok_or_else(|| anyhow::anyhow!("no subtitle"))?.with_extension(self.format.extension());
+        Transcript {
+            processing_time: Duration::ZERO,
+            utterances: self.utterances.clone(),
+            word_utterances: None,
+        }.save(&path, self.format)?;
+        Ok(path)
+    }
+
+    fn transform<F: FnOnce(&mut Transcript) -> anyhow::Result<()>>(&mut self, f: F) {
+        self.checkpoint();
+        let mut transcript = Transcript {
+            processing_time: Duration::ZERO,
+            utterances: std::mem::take(&mut self.utterances),
+            word_utterances: None,
+        };
+        if let Err(e) = f(&mut transcript) {
+            self.status = e.to_string();
+        }
+        self.utterances = transcript.utterances;
+    }
+
+    fn checkpoint(&mut self) {
+        if self.undo.last() != Some(&self.utterances) {
+            self.undo.push(self.utterances.clone());
+            self.redo.clear();
+        }
+    }
+
+    pub fn undo(&mut self) {
+        while let Some(utterances) = self.undo.pop() {
+            if utterances != self.utterances {
+                self.redo.push(std::mem::replace(&mut self.utterances, utterances));
+                break;
+            }
+        }
+    }
+
+    pub fn redo(&mut self) {
+        if let Some(utterances) = self.redo.pop() {
+            self.undo.push(std::mem::replace(&mut self.utterances, utterances));
+        }
+    }
+
+    pub fn check(&mut self) {
+        let transcript = Transcript {
+            processing_time: Duration::ZERO,
+            utterances: self.utterances.clone(),
+            word_utterances: None,
+        };
+        self.issues = check::check(&transcript, &self.limits, self.length);
+        self.checking = true;
+    }
+
+    pub fn auto_sync(&mut self) {
+        let Some(audio) = self.audio.clone() else {
+            self.status = "未选择音频".to_string();
+            return;
+        };
+        let transcript = Transcript {
+            processing_time: Duration::ZERO,
+            utterances: self.utterances.clone(),
+            word_utterances: None,
+        };
+        let drift = self.drift;
+        let correction = self.correction.clone();
+        self.status = "同步中".to_string();
+        tokio::spawn(async move {
+            let result = utils::read_file(audio).map(|samples| auto_sync(&transcript, &utils::speech(&samples), 6000, drift));
+            *correction.lock().unwrap() = Some(result);
+        });
+    }
+
+    pub fn split(&mut self, i: usize) {
+        let chars = self.utterances[i].text.trim().chars().collect::<Vec<_>>();
+        if chars.len() < 2 {
+            return;
+        }
+        self.checkpoint();
+        let utterance = &self.utterances[i];
+        let middle = chars.len() / 2;
+        let at = (0..middle)
+            .flat_map(|d| [middle - d, middle + d])
+            .find(|&j| j > 0 && j < chars.len() && (chars[j].is_whitespace() || is_cjk(chars[j])))
+            .unwrap_or(middle);
+        let first = chars[..at].iter().collect::<String>().trim().to_string();
+        let second = chars[at..].iter().collect::<String>().trim().to_string();
+        let time = utterance.start + (utterance.end - utterance.start) * at as i64 / chars.len() as i64;
+        let next = Utterance { start: time, end: utterance.end, text: second };
+        self.utterances[i].end = time;
+        self.utterances[i].text = first;
+        self.utterances.insert(i + 1, next);
+    }
+
+    pub fn merge(&mut self, i: usize) {
+        if i + 1 >= self.utterances.len() {
+            return;
+        }
+        self.checkpoint();
+        let next = self.utterances.remove(i + 1);
+        let utterance = &mut self.utterances[i];
+        let cjk = utterance.text.trim_end().ends_with(is_cjk) || next.text.trim_start().starts_with(is_cjk);
+        utterance.text = format!("{}{}{}", utterance.text.trim_end(), if cjk { "" } else { " " }, next.text.trim_start());
+        utterance.end = utterance.end.max(next.end);
+    }
+
+    pub fn insert(&mut self, i: usize) {
+        self.checkpoint();
+        let start = self.utterances.get(i).map(|u| u.end).unwrap_or_default();
+        let end = match self.utterances.get(i + 1) {
+            Some(next) if next.start > start => next.start.min(start + 200),
+            _ => start + 200,
+        };
+        self.utterances.insert((i + 1).min(self.utterances.len()), Utterance { start, end, text: String::new() });
+    }
+
+    pub fn delete(&mut self, i: usize) {
+        self.checkpoint();
+        self.utterances.remove(i);
+    }
+
+    pub fn show(&mut self, ctx: &Context, limits: &Segmentation) {
+        let builder = ViewportBuilder::default()
+            .with_title("字幕编辑")
+            .with_inner_size([640.0, 540.0]);
+        ctx.show_viewport_immediate(ViewportId::from_hash_of("editor"), builder, |ctx, _| {
+            let checked = self.checking.then(|| self.utterances.clone());
+            egui::TopBottomPanel::top("editor_toolbar").show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    if ui.add_enabled(!self.undo.is_empty(), egui::Button::new("撤销")).clicked() {
+                        self.undo();
+                    }
+                    if ui.add_enabled(!self.redo.is_empty(), egui::Button::new("重做")).clicked() {
+                        self.redo();
+                    }
+                    if ui.button("插入").clicked() {
+                        self.insert(self.utterances.len().saturating_sub(1));
+                    }
+                    select(ui, "格式", &mut self.format);
+                    if ui.button("保存").clicked() {
+                        self.status = match self.save() {
+                            Ok(path) => format!("已保存: {}", path.file_name().unwrap().to_str().unwrap()),
+                            Err(e) => e.to_string(),
+                        };
+                    }
+                    ui.label(&self.status);
+                });
+                ui.horizontal(|ui| {
+                    if ui.button("检查").clicked() {
+                        self.check();
+                    }
+                    if ui.add_enabled(!self.issues.is_empty(), egui::Button::new("自动修复")).clicked() {
+                        let (limits, length) = (self.limits.clone(), self.length);
+                        self.transform(|t| {
+                            check::fix(t, &limits, length);
+                            Ok(())
+                        });
+                        self.check();
+                    }
+                    ui.label(format!("问题: {}", self.issues.len()));
+                });
+                if !self.issues.is_empty() {
+                    egui::ScrollArea::vertical().id_source("issues").max_height(80.0).show(ui, |ui| {
+                        for issue in self.issues.iter() {
+                            ui.label(format!("#{} {} {}", issue.index, issue.rule, issue.message));
+                        }
+                    });
+                }
+                egui::CollapsingHeader::new("时间调整").show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("平移");
+                        ui.add(DragValue::new(&mut self.offset).speed(0.01).suffix("s"));
+                        if ui.button("应用").clicked() {
+                            let offset = (self.offset * 100.0).round() as i64;
+                            self.transform(|t| {
+                                t.shift(offset);
+                                Ok(())
+                            });
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("同步点");
+                        for (from, to) in self.sync.iter_mut() {
+                            ui.add(time_value(from));
+                            ui.label("->");
+                            ui.add(time_value(to));
+                        }
+                        if ui.button("应用").clicked() {
+                            let [a, b] = self.sync;
+                            self.transform(|t| t.stretch(a, b));
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        if ui.add_enabled(self.audio.is_some(), egui::Button::new("自动同步")).clicked() {
+                            self.auto_sync();
+                        }
+                        ui.checkbox(&mut self.drift, "校正速度漂移");
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("帧率");
+                        ui.add(DragValue::new(&mut self.framerate.0).clamp_range(1.0..=240.0).speed(0.001));
+                        ui.label("->");
+                        ui.add(DragValue::new(&mut self.framerate.1).clamp_range(1.0..=240.0).speed(0.001));
+                        if ui.button("应用").clicked() {
+                            let (from, to) = self.framerate;
+                            self.transform(|t| t.convert_framerate(from, to));
+                        }
+                    });
+                });
+            });
+            let correction = self.correction.lock().unwrap().take();
+            match correction {
+                Some(Ok(correction)) => {
+                    self.status = format!("偏移: {:.2}s, 速度: x{:.5}", correction.offset as f64 / 100.0, 1.0 + correction.drift);
+                    self.transform(|t| {
+                        correction.apply(t);
+                        Ok(())
+                    });
+                }
+                Some(Err(e)) => self.status = format!("同步失败: {}", e),
+                None => {}
+            }
+            let before = self.utterances.clone();
+            let mut done = false;
+            let mut acted = false;
+            egui::TopBottomPanel::top("editor_timeline").show(ctx, |ui| {
+                done |= self.timeline(ui);
+            });
+            egui::CentralPanel::default().show(ctx, |ui| {
+                let mut action = None;
+                egui::ScrollArea::vertical().show_rows(ui, 52.0, self.utterances.len(), |ui, rows| {
+                    for i in rows {
+                        let overlap = i > 0 && self.utterances[i - 1].end > self.utterances[i].start;
+                        let utterance = &mut self.utterances[i];
+                        ui.horizontal(|ui| {
+                            ui.vertical(|ui| {
+                                for (time, color) in [(&mut utterance.start, overlap), (&mut utterance.end, false)] {
+                                    let response = ui.add(time_value(time));
+                                    if color {
+                                        ui.painter().rect_stroke(response.rect, 2.0, (1.0, Color32::RED));
+                                    }
+                                    done |= response.drag_released() || response.lost_focus();
+                                }
+                            });
+                            let response = ui.add(egui::TextEdit::multiline(&mut utterance.text).desired_rows(2).desired_width(360.0));
+                            done |= response.lost_focus();
+                            ui.vertical(|ui| {
+                                ui.horizontal(|ui| {
+                                    if ui.small_button("拆分").clicked() {
+                                        action = Some((i, Editor::split as fn(&mut Editor, usize)));
+                                    }
+                                    if ui.small_button("合并").clicked() {
+                                        action = Some((i, Editor::merge));
+                                    }
+                                });
+                                ui.horizontal(|ui| {
+                                    if ui.small_button("插入").clicked() {
+                                        action = Some((i, Editor::insert));
+                                    }
+                                    if ui.small_button("删除").clicked() {
+                                        action = Some((i, Editor::delete));
+                                    }
+                                });
+                            });
+                        });
+                    }
+                });
+                if let Some((i, action)) = action {
+                    action(self, i);
+                    acted = true;
+                    done = true;
+                }
+            });
+            if self.utterances != before && !self.editing && !acted {
+                self.undo.push(before);
+                self.redo.clear();
+                self.editing = true;
+            }
+            if done {
+                self.editing = false;
+            }
+            let changed = self.limits != *limits;
+            if changed {
+                self.limits = limits.clone();
+            }
+            if checked.is_some_and(|checked| changed || checked != self.utterances) {
+                self.check();
+            }
+            if ctx.memory(|m| m.focus().is_none()) && ctx.input(|i| i.modifiers.command && i.key_pressed(egui::Key::Z)) {
+                if ctx.input(|i| i.modifiers.shift) {
+                    self.redo();
+                } else {
+                    self.undo();
+                }
+            }
+            if ctx.input(|i| i.viewport().close_requested()) {
+                self.open = false;
+            }
+        });
+    }
+
+    fn timeline(&mut self, ui: &mut Ui) -> bool {
+        let mut done = false;
+        ui.horizontal(|ui| {
+            ui.label("缩放");
+            ui.add(egui::Slider::new(&mut self.zoom, 5.0..=500.0).logarithmic(true).suffix("px/s"));
+        });
+        let waveform = self.waveform.lock().unwrap();
+        let end = self.utterances.iter().map(|u| u.end).max().unwrap_or_default().max(waveform.len() as i64);
+        let zoom = self.zoom;
+        egui::ScrollArea::horizontal().id_source("timeline").show(ui, |ui| {
+            let (rect, _) = ui.allocate_exact_size(Vec2::new(end as f32 / 100.0 * zoom + 20.0, 100.0), Sense::hover());
+            let x = |t: i64| rect.left() + t as f32 / 100.0 * zoom;
+            let painter = ui.painter_at(rect);
+            painter.rect_filled(rect, 0.0, Color32::from_gray(30));
+
+            let visible = ui.clip_rect().intersect(rect);
+            let middle = rect.center().y;
+            let mut px = visible.left();
+            while px < visible.right() {
+                let from = ((px - rect.left()) / zoom * 100.0).max(0.0) as usize;
+                let to = (((px + 1.0 - rect.left()) / zoom * 100.0) as usize).max(from + 1);
+                let peak = waveform.get(from..to.min(waveform.len())).unwrap_or_default().iter().fold(0.0f32, |p, s| p.max(*s));
+                painter.line_segment([Pos2::new(px, middle - peak * 45.0), Pos2::new(px, middle + peak * 45.0)], Stroke::new(1.0, Color32::from_rgb(90, 160, 90)));
+                px += 1.0;
+            }
+            let step = if zoom >= 50.0 { 1 } else if zoom >= 10.0 { 10 } else { 60 };
+            for second in (0..=end / 100).step_by(step) {
+                let sx = x(second * 100);
+                if sx >= visible.left() && sx <= visible.right() {
+                    painter.line_segment([Pos2::new(sx, rect.top()), Pos2::new(sx, rect.top() + 4.0)], Stroke::new(1.0, Color32::GRAY));
+                }
+            }
+
+            for i in 0..self.utterances.len() {
+                let utterance = &self.utterances[i];
+                let cue = Rect::from_min_max(Pos2::new(x(utterance.start), rect.top() + 60.0), Pos2::new(x(utterance.end), rect.bottom() - 4.0));
+                if !cue.intersects(visible) {
+                    continue;
+                }
+                painter.rect(cue, 2.0, Color32::from_rgba_unmultiplied(70, 110, 200, 160), Stroke::new(1.0, Color32::from_rgb(120, 160, 255)));
+                painter.with_clip_rect(cue.intersect(visible)).text(
+                    cue.left_center() + Vec2::new(4.0, 0.0),
+                    egui::Align2::LEFT_CENTER,
+                    utterance.text.replace('\n', " "),
+                    egui::FontId::proportional(12.0),
+                    Color32::WHITE,
+                );
+
+                let handle = |left: f32| Rect::from_min_max(Pos2::new(left - 3.0, cue.top()), Pos2::new(left + 3.0, cue.bottom()));
+                let start = ui.interact(handle(cue.left()), Id::new(("cue_start", i)), Sense::drag()).on_hover_cursor(egui::CursorIcon::ResizeHorizontal);
+                let end = ui.interact(handle(cue.right()), Id::new(("cue_end", i)), Sense::drag()).on_hover_cursor(egui::CursorIcon::ResizeHorizontal);
+                let time = |r: &egui::Response| r.interact_pointer_pos().map(|p| ((p.x - rect.left()) / zoom * 100.0).round() as i64);
+                let utterance = &mut self.utterances[i];
+                if let Some(time) = time(&start).filter(|_| start.dragged()) {
+                    utterance.start = time.min(utterance.end - 1).max(0);
+                }
+                if let Some(time) = time(&end).filter(|_| end.dragged()) {
+                    utterance.end = time.max(utterance.start + 1);
+                }
+                done |= start.drag_released() || end.drag_released();
+            }
+        });
+        done
+    }
+}
+
+fn time_value(time: &mut i64) -> DragValue<'_> {
+    DragValue::new(time)
+        .clamp_range(0..=i64::MAX)
+        .custom_formatter(|t, _| {
+            let t = t as i64;
+            format!("{:02}:{:02}.{:02}", t / 6000, t / 100 % 60, t % 100)
+        })
+        .custom_parser(|s| parse_time(s).map(|t| t as f64))
+}
@@ -1,8 +1,10 @@
 #![windows_subsystem = "windows"]
 
+use clap::Parser;
 use eframe::NativeOptions;
 use egui::{Vec2, ViewportBuilder};
 
+use crate::cli::Cli;
 use crate::conv::Conv;
 
 mod ui;
@@ -11,16 +13,44 @@ mod utils;
 mod whisper;
 mod config;
 mod conv;
+mod cli;
+mod editor;
+mod align;
+mod check;
 
 #[tokio::main]
 async fn main() {
-    run().await;
+    match Cli::parse().command {
+        Some(command) => {
+            attach_console();
+            if let Err(e) = cli::run(command).await {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+        None => run().await,
+    }
 }
 
+#[cfg(windows)]
+fn attach_console() {
+    const ATTACH_PARENT_PROCESS: u32 = u32::MAX;
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn AttachConsole(process: u32) -> i32;
+    }
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
+#[cfg(not(windows))]
+fn attach_console() {}
+
 async fn run() {
     let viewport = ViewportBuilder {
         resizable: Some(false),
-        inner_size: Some(Vec2::new(400.0, 500.0)),
+        inner_size: Some(Vec2::new(400.0, 540.0)),
         maximize_button: Some(false),
         ..Default::default()
     };
@@ -1,99 +1,534 @@
+use std::fmt::Display;
+use std::path::Path;
 use std::sync::atomic::Ordering;
 
 use clap_builder::ValueEnum;
 use eframe::Frame;
-use egui::{ComboBox, Context, ProgressBar};
+use egui::{Align, Align2, Color32, ComboBox, Context, DragValue, FontId, Painter, Pos2, ProgressBar, Rect, Sense, Ui, Vec2};
 
-use crate::config::{DOWNLOADED, FILE_SIZE, Language, Model};
+use crate::config::{Background, DOWNLOADED, FILE_SIZE, Fill, Hallucination, Language, MergeConfig, Model, Motion, Position, Preset, Resolution, SlideTiming, SubtitleMode, SubtitleStyle, Template, Visualizer};
 use crate::conv::Conv;
-use crate::utils::{DOWNLOADING, MERGE, WHISPER};
+use crate::utils::{burn_style, duration, DOWNLOADING, MERGE, PREVIEW, WHISPER};
 
 impl eframe::App for Conv {
     fn update(&mut self, ctx: &Context, _: &mut Frame) {
         ctx.request_repaint();
 
         egui::CentralPanel::default().show(ctx, |ui| {
-            if ui.button("选择音频").clicked() {
-                self.open_audio(self.files.clone());
-            }
-            ui.label(format!("音频: {}", if let Some(ref p) = self.files.lock().unwrap().audio {
-                p.file_name().unwrap().to_str().unwrap()
-            } else {
-                "None"
-            }));
-
-            if ui.button("选择背景图片").clicked() {
-                self.open_image(self.files.clone());
-            }
-            ui.label(format!("背景图片: {}", if let Some(ref p) = self.files.lock().unwrap().image {
-                p.file_name().unwrap().to_str().unwrap()
-            } else {
-                "None"
-            }));
-
-            if ui.button("选择字幕").clicked() {
-                self.open_subtitle(self.files.clone());
-            }
-            ui.label(format!("字幕: {}", if let Some(ref p) = self.files.lock().unwrap().subtitle {
-                p.file_name().unwrap().to_str().unwrap()
-            } else {
-                "None"
-            }));
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                if ui.button("选择音频").clicked() {
+                    self.open_audio(self.files.clone());
+                }
+                ui.label(format!("音频: {}", if let Some(ref p) = self.files.lock().unwrap().audio {
+                    p.file_name().unwrap().to_str().unwrap()
+                } else {
+                    "None"
+                }));
 
+                if ui.button("选择背景图片/视频").clicked() {
+                    self.open_image(self.files.clone());
+                }
+                let mut files = self.files.lock().unwrap();
+                match files.images.len() {
+                    0 => {
+                        let generated = &mut self.config.merge.generated;
+                        ui.horizontal(|ui| {
+                            select(ui, "生成背景", &mut generated.kind);
+                            match generated.kind {
+                                Background::Color => {
+                                    ui.color_edit_button_srgb(&mut generated.color);
+                                }
+                                Background::Gradient => {
+                                    ui.color_edit_button_srgb(&mut generated.color);
+                                    ui.color_edit_button_srgb(&mut generated.color2);
+                                }
+                                Background::Cover => {}
+                            }
+                        });
+                        ui.horizontal(|ui| {
+                            ui.checkbox(&mut generated.text, "显示标题/艺术家");
+                            if generated.text {
+                                ui.color_edit_button_srgb(&mut generated.text_color);
+                            }
+                        });
+                    }
+                    1 => {
+                        ui.label(format!("背景图片: {}", files.images[0].path.file_name().unwrap().to_str().unwrap()));
+                    }
+                    _ => {
+                        let slideshow = &mut self.config.merge.slideshow;
+                        ui.horizontal(|ui| {
+                            let timing = slideshow.timing;
+                            select(ui, "切换", &mut slideshow.timing);
+                            if timing != slideshow.timing && slideshow.timing == SlideTiming::Manual && files.images.iter().all(|s| s.start <= 0.0) {
+                                if let Some(duration) = files.audio.as_ref().and_then(|audio| duration(audio).ok()) {
+                                    let count = files.images.len();
+                                    for (i, slide) in files.images.iter_mut().enumerate() {
+                                        slide.start = (i as f64 * duration / count as f64).round();
+                                    }
+                                }
+                            }
+                            ui.add(DragValue::new(&mut slideshow.crossfade).clamp_range(0.0..=5.0).speed(0.1).suffix("s"));
+                            ui.label("淡入淡出");
+                        });
+                        for slide in files.images.iter_mut() {
+                            ui.horizontal(|ui| {
+                                if slideshow.timing == SlideTiming::Manual {
+                                    ui.add(DragValue::new(&mut slide.start).clamp_range(0.0..=f64::MAX).speed(0.5).suffix("s"));
+                                }
+                                ui.label(slide.path.file_name().unwrap().to_str().unwrap());
+                            });
+                        }
+                    }
+                }
+                drop(files);
 
-            ui.separator();
+                if ui.button("添加字幕").clicked() {
+                    self.open_subtitle(self.files.clone());
+                }
+                let mut files = self.files.lock().unwrap();
+                let mut burn = None;
+                let mut remove = None;
+                let mut edit = None;
+                for (i, subtitle) in files.subtitles.iter_mut().enumerate() {
+                    ui.horizontal(|ui| {
+                        ui.label(subtitle.path.file_name().unwrap().to_str().unwrap());
+                        ComboBox::from_id_source(("lang", i))
+                            .width(60.0)
+                            .selected_text(<&str>::from(subtitle.lang))
+                            .show_ui(ui, |ui| {
+                                ui.style_mut().wrap = Some(false);
+                                for l in Language::value_variants() {
+                                    ui.selectable_value(&mut subtitle.lang, *l, <&str>::from(*l));
+                                }
+                            });
+                        ComboBox::from_id_source(("mode", i))
+                            .width(80.0)
+                            .selected_text(format!("{}", subtitle.mode))
+                            .show_ui(ui, |ui| {
+                                ui.style_mut().wrap = Some(false);
+                                for m in SubtitleMode::value_variants() {
+                                    if ui.selectable_value(&mut subtitle.mode, *m, format!("{}", *m)).clicked() && *m == SubtitleMode::Burn {
+                                        burn = Some(i);
+                                    }
+                                }
+                            });
+                        if ui.button("编辑").clicked() {
+                            edit = Some(subtitle.path.clone());
+                        }
+                        if ui.button("删除").clicked() {
+                            remove = Some(i);
+                        }
+                    });
+                }
+                if let Some(burn) = burn {
+                    for (i, subtitle) in files.subtitles.iter_mut().enumerate() {
+                        if i != burn && subtitle.mode == SubtitleMode::Burn {
+                            subtitle.mode = SubtitleMode::Soft;
+                        }
+                    }
+                }
+                if let Some(remove) = remove {
+                    files.subtitles.remove(remove);
+                }
+                if let Some(path) = edit {
+                    self.editor.load(path, files.audio.clone()).ok();
+                }
 
-            if ui.button("合并音频/图片/字幕").clicked() {
-                if !MERGE.load(Ordering::Relaxed) {
-                    self.ffmpeg_merge();
+                let mut pick_logo = false;
+                egui::CollapsingHeader::new("标题/水印/Logo").show(ui, |ui| {
+                    let overlay = &mut self.config.merge.overlay;
+                    let title = &mut overlay.title;
+                    ui.checkbox(&mut title.enabled, "片头标题");
+                    if title.enabled {
+                        ui.text_edit_singleline(&mut title.text);
+                        ui.horizontal(|ui| {
+                            ui.add(egui::TextEdit::singleline(&mut title.font).desired_width(120.0));
+                            ui.add(DragValue::new(&mut title.size).clamp_range(8..=400));
+                            ui.color_edit_button_srgb(&mut title.color);
+                        });
+                        ui.horizontal(|ui| {
+                            select(ui, "位置", &mut title.position);
+                            ui.add(DragValue::new(&mut title.start).clamp_range(0.0..=f64::MAX).speed(0.1).suffix("s"));
+                            ui.label("-");
+                            ui.add(DragValue::new(&mut title.end).clamp_range(title.start..=f64::MAX).speed(0.1).suffix("s"));
+                        });
+                    }
+                    let watermark = &mut overlay.watermark;
+                    ui.checkbox(&mut watermark.enabled, "水印");
+                    if watermark.enabled {
+                        ui.text_edit_singleline(&mut watermark.text);
+                        ui.horizontal(|ui| {
+                            ui.add(egui::TextEdit::singleline(&mut watermark.font).desired_width(120.0));
+                            ui.add(DragValue::new(&mut watermark.size).clamp_range(8..=400));
+                            ui.color_edit_button_srgb(&mut watermark.color);
+                        });
+                        select(ui, "位置", &mut watermark.position);
+                        ui.add(egui::Slider::new(&mut watermark.opacity, 0.0..=1.0).text("不透明度"));
+                    }
+                    ui.horizontal(|ui| {
+                        if ui.button("选择Logo").clicked() {
+                            pick_logo = true;
+                        }
+                        if let Some(ref logo) = files.logo {
+                            ui.label(logo.file_name().unwrap().to_str().unwrap());
+                            if ui.button("删除").clicked() {
+                                files.logo = None;
+                            }
+                        }
+                    });
+                    if files.logo.is_some() {
+                        let logo = &mut overlay.logo;
+                        ui.horizontal(|ui| {
+                            select(ui, "位置", &mut logo.position);
+                            ui.add(DragValue::new(&mut logo.scale).clamp_range(1..=100).suffix("%"));
+                            ui.label("宽");
+                        });
+                        ui.add(egui::Slider::new(&mut logo.opacity, 0.0..=1.0).text("不透明度"));
+                    }
+                    ui.horizontal(|ui| {
+                        ComboBox::from_label("模板")
+                            .width(100.0)
+                            .selected_text(&self.template)
+                            .show_ui(ui, |ui| {
+                                ui.style_mut().wrap = Some(false);
+                                for name in self.templates.iter() {
+                                    if ui.selectable_label(self.template == *name, name).clicked() {
+                                        match Template::load(name) {
+                                            Ok(template) => {
+                                                *overlay = template.overlay;
+                                                files.logo = template.logo;
+                                                self.template_status.clear();
+                                            }
+                                            Err(e) => self.template_status = e.to_string(),
+                                        }
+                                        self.template = name.clone();
+                                    }
+                                }
+                            });
+                        ui.add(egui::TextEdit::singleline(&mut self.template).desired_width(80.0));
+                        if ui.button("保存").clicked() {
+                            self.template_status = match (Template { overlay: overlay.clone(), logo: files.logo.clone() }).save(&self.template) {
+                                Ok(()) => {
+                                    self.templates = Template::list();
+                                    "已保存".to_string()
+                                }
+                                Err(e) => e.to_string(),
+                            };
+                        }
+                    });
+                    if !self.template_status.is_empty() {
+                        ui.label(&self.template_status);
+                    }
+                });
+                drop(files);
+                if pick_logo {
+                    self.open_logo(self.files.clone());
                 }
-            }
-            ui.label(if MERGE.load(Ordering::Relaxed) { "合并中" } else { "合并结束" });
 
-            ui.separator();
+                ui.separator();
 
-            ui.label("Whisper");
-            ComboBox::from_label("语言")
-                .selected_text(<&str>::from(self.config.lang))
-                .show_ui(ui, |ui| {
-                    ui.style_mut().wrap = Some(false);
-                    for i in Language::value_variants() {
-                        ui.selectable_value(&mut self.config.lang, *i, <&str>::from(*i));
+                let merge = &mut self.config.merge;
+                if select(ui, "预设", &mut merge.preset) {
+                    merge.encoder = merge.preset.encoder();
+                }
+                egui::CollapsingHeader::new("画面设置").show(ui, |ui| {
+                    let canvas = &mut merge.canvas;
+                    ui.horizontal(|ui| {
+                        select(ui, "分辨率", &mut canvas.resolution);
+                        if !matches!(canvas.resolution, Resolution::Source | Resolution::Custom) {
+                            select(ui, "比例", &mut canvas.aspect);
+                        }
+                    });
+                    if canvas.resolution == Resolution::Custom {
+                        ui.horizontal(|ui| {
+                            ui.add(DragValue::new(&mut canvas.width).clamp_range(2..=7680));
+                            ui.label("x");
+                            ui.add(DragValue::new(&mut canvas.height).clamp_range(2..=4320));
+                        });
+                    }
+                    ui.horizontal(|ui| {
+                        select(ui, "填充", &mut canvas.fill);
+                        if canvas.fill == Fill::Color {
+                            ui.color_edit_button_srgb(&mut canvas.color);
+                        }
+                    });
+                    let ken_burns = &mut merge.ken_burns;
+                    select(ui, "动态效果", &mut ken_burns.motion);
+                    if ken_burns.motion != Motion::None {
+                        ui.horizontal(|ui| {
+                            ui.add(DragValue::new(&mut ken_burns.amount).clamp_range(1.0..=2.0).speed(0.01).prefix("x"));
+                            ui.label("幅度");
+                            ui.add(DragValue::new(&mut ken_burns.period).clamp_range(2.0..=600.0).speed(0.5).suffix("s"));
+                            ui.label("周期");
+                        });
+                    }
+                });
+                egui::CollapsingHeader::new("字幕样式").show(ui, |ui| {
+                    let style = &mut merge.subtitle_style;
+                    ui.checkbox(&mut style.enabled, "自定义烧录字幕样式");
+                    if style.enabled {
+                        ui.horizontal(|ui| {
+                            ui.add(egui::TextEdit::singleline(&mut style.font).desired_width(120.0));
+                            ui.add(DragValue::new(&mut style.size).clamp_range(4..=200));
+                            ui.label("字号");
+                            ui.checkbox(&mut style.bold, "粗体");
+                        });
+                        ui.horizontal(|ui| {
+                            ui.color_edit_button_srgb(&mut style.color);
+                            ui.label("文字");
+                            ui.color_edit_button_srgb(&mut style.outline_color);
+                            ui.label("描边");
+                            ui.add(DragValue::new(&mut style.outline).clamp_range(0.0..=10.0).speed(0.1));
+                            ui.label("阴影");
+                            ui.add(DragValue::new(&mut style.shadow).clamp_range(0.0..=10.0).speed(0.1));
+                        });
+                        ui.horizontal(|ui| {
+                            select(ui, "对齐", &mut style.alignment);
+                            ui.add(DragValue::new(&mut style.margin).clamp_range(0..=300));
+                            ui.label("垂直边距");
+                        });
+                        subtitle_preview(ui, style);
                     }
                 });
-            ui.horizontal(|ui| {
-                ComboBox::from_label("模型")
-                    .selected_text(format!("{}", self.config.model))
+                egui::CollapsingHeader::new("音频可视化").show(ui, |ui| {
+                    let visualization = &mut merge.visualization;
+                    select(ui, "样式", &mut visualization.kind);
+                    if visualization.kind != Visualizer::None {
+                        select(ui, "位置", &mut visualization.position);
+                        ui.horizontal(|ui| {
+                            ui.add(DragValue::new(&mut visualization.width).clamp_range(1..=100).suffix("%"));
+                            ui.label("宽");
+                            ui.add(DragValue::new(&mut visualization.height).clamp_range(1..=100).suffix("%"));
+                            ui.label("高");
+                            ui.color_edit_button_srgb(&mut visualization.color);
+                        });
+                        ui.add(egui::Slider::new(&mut visualization.opacity, 0.0..=1.0).text("不透明度"));
+                    }
+                });
+                egui::CollapsingHeader::new("编码设置").show(ui, |ui| {
+                    let encoder = merge.encoder.clone();
+                    select(ui, "格式", &mut merge.encoder.container);
+                    select(ui, "视频编码", &mut merge.encoder.video_codec);
+                    select(ui, "速度", &mut merge.encoder.speed);
+                    ui.horizontal(|ui| {
+                        ui.add(DragValue::new(&mut merge.encoder.crf).clamp_range(0..=merge.encoder.video_codec.max_crf()));
+                        ui.label("CRF");
+                        ui.add(DragValue::new(&mut merge.encoder.video_bitrate).suffix("k"));
+                        ui.label("视频码率(0为CRF)");
+                    });
+                    select(ui, "音频编码", &mut merge.encoder.audio_codec);
+                    ui.horizontal(|ui| {
+                        ui.add(DragValue::new(&mut merge.encoder.audio_bitrate).suffix("k"));
+                        ui.label("音频码率");
+                        ui.add(DragValue::new(&mut merge.encoder.framerate).clamp_range(1..=120));
+                        ui.label("帧率");
+                    });
+                    ui.checkbox(&mut merge.still, "静态图片加速(需要FFmpeg 5.1+)");
+                    if encoder != merge.encoder {
+                        merge.preset = Preset::Custom;
+                    }
+                    if let Err(e) = merge.encoder.check() {
+                        ui.colored_label(Color32::RED, e.to_string());
+                    }
+                });
+                let valid = self.config.merge.encoder.check().is_ok();
+                egui::CollapsingHeader::new("预览").show(ui, |ui| {
+                    let mut render = false;
+                    if ui.button(if PREVIEW.load(Ordering::Relaxed) { "渲染中" } else { "刷新预览" }).clicked() {
+                        render = true;
+                    }
+                    let mut preview = self.preview.lock().unwrap();
+                    if let Some(image) = preview.image.take() {
+                        preview.texture = Some(ctx.load_texture("preview", image, Default::default()));
+                    }
+                    if let Some(ref texture) = preview.texture {
+                        let size = texture.size_vec2();
+                        let width = ui.available_width();
+                        let (rect, _) = ui.allocate_exact_size(Vec2::new(width, width * size.y / size.x), Sense::hover());
+                        let painter = ui.painter_at(rect);
+                        painter.image(texture.id(), rect, Rect::from_min_max(Pos2::ZERO, Pos2::new(1.0, 1.0)), Color32::WHITE);
+                        let time = (preview.time * 100.0) as i64;
+                        if let Some((path, utterance)) = preview.transcript
+                            .as_ref()
+                            .and_then(|(path, t)| Some((path, t.utterances.iter().find(|u| u.start <= time && time < u.end)?))) {
+                            let style = preview_style(path, &self.config.merge, texture.size());
+                            paint_subtitle(&painter, rect, &style, &utterance.text);
+                        }
+                    }
+                    let duration = preview.duration.max(preview.time);
+                    let slider = ui.add(egui::Slider::new(&mut preview.time, 0.0..=duration).suffix("s"));
+                    if slider.drag_released() || (slider.changed() && !slider.dragged()) {
+                        render = true;
+                    }
+                    let mut excerpt = false;
+                    ui.horizontal(|ui| {
+                        ui.add(DragValue::new(&mut preview.length).clamp_range(10.0..=30.0).speed(0.5).suffix("s"));
+                        if ui.add_enabled(valid, egui::Button::new("渲染片段")).clicked() && !MERGE.load(Ordering::Relaxed) {
+                            excerpt = true;
+                        }
+                    });
+                    if let Some(ref output) = preview.output {
+                        ui.label(format!("片段: {}", output.file_name().unwrap().to_str().unwrap()));
+                    }
+                    drop(preview);
+                    if render && !PREVIEW.load(Ordering::Relaxed) {
+                        self.render_preview();
+                    }
+                    if excerpt {
+                        self.render_excerpt();
+                    }
+                });
+
+                if ui.add_enabled(valid, egui::Button::new("合并音频/图片/字幕")).clicked() {
+                    if !MERGE.load(Ordering::Relaxed) {
+                        self.ffmpeg_merge();
+                    }
+                }
+                ui.label(if MERGE.load(Ordering::Relaxed) { "合并中" } else { "合并结束" });
+                let status = self.merge_status.lock().unwrap().clone();
+                if !status.is_empty() {
+                    ui.colored_label(Color32::RED, status);
+                }
+
+                ui.separator();
+
+                ui.label("Whisper");
+                ComboBox::from_label("语言")
+                    .selected_text(<&str>::from(self.config.lang))
                     .show_ui(ui, |ui| {
                         ui.style_mut().wrap = Some(false);
-                        for i in Model::value_variants() {
-                            ui.selectable_value(&mut self.config.model, *i, format!("{}", *i));
+                        for i in Language::value_variants() {
+                            ui.selectable_value(&mut self.config.lang, *i, <&str>::from(*i));
                         }
                     });
-                if ui.button("下载模型").clicked() {
-                    DOWNLOADING.store(false, Ordering::Relaxed);
-                    let model = self.config.model;
-                    if std::fs::remove_file(model.get_path()).is_err() {}
-                    tokio::spawn(async move {
-                        if model.download().await.is_err() {
-                            DOWNLOADING.store(false, Ordering::Relaxed);
+                ui.horizontal(|ui| {
+                    ComboBox::from_label("模型")
+                        .selected_text(format!("{}", self.config.model))
+                        .show_ui(ui, |ui| {
+                            ui.style_mut().wrap = Some(false);
+                            for i in Model::value_variants() {
+                                ui.selectable_value(&mut self.config.model, *i, format!("{}", *i));
+                            }
+                        });
+                    if ui.button("下载模型").clicked() {
+                        DOWNLOADING.store(false, Ordering::Relaxed);
+                        let model = self.config.model;
+                        if std::fs::remove_file(model.get_path()).is_err() {}
+                        tokio::spawn(async move {
+                            if model.download().await.is_err() {
+                                DOWNLOADING.store(false, Ordering::Relaxed);
+                            }
+                        });
+                    }
+                });
+
+                ui.checkbox(&mut self.config.bilingual, "双语字幕(原文+英文翻译)");
+                ui.checkbox(&mut self.config.vad, "只识别有声段落(跳过静音)");
+                ComboBox::from_label("重复/幻觉字幕")
+                    .selected_text(format!("{}", self.config.hallucination))
+                    .show_ui(ui, |ui| {
+                        for i in Hallucination::value_variants() {
+                            ui.selectable_value(&mut self.config.hallucination, *i, format!("{}", *i));
                         }
                     });
+                let segmentation = &mut self.config.segmentation;
+                ui.checkbox(&mut segmentation.enabled, "按词时间重新分段");
+                if segmentation.enabled {
+                    ui.horizontal(|ui| {
+                        ui.add(DragValue::new(&mut segmentation.chars_per_line).clamp_range(10..=100));
+                        ui.label("每行字符");
+                        ui.add(DragValue::new(&mut segmentation.lines).clamp_range(1..=4));
+                        ui.label("行数");
+                    });
+                    ui.horizontal(|ui| {
+                        ui.add(DragValue::new(&mut segmentation.duration).clamp_range(1.0..=30.0).speed(0.1).suffix("s"));
+                        ui.label("最长");
+                        ui.add(DragValue::new(&mut segmentation.cps).clamp_range(5.0..=50.0).speed(0.5));
+                        ui.label("字符/秒");
+                    });
+                }
+                if ui.button("音频 -> 字幕").clicked() {
+                    if !WHISPER.load(Ordering::Relaxed) && !DOWNLOADING.load(Ordering::Relaxed) {
+                        self.whisper();
+                    }
+                }
+                if ui.button("文稿/歌词对齐").clicked() && !WHISPER.load(Ordering::Relaxed) && !DOWNLOADING.load(Ordering::Relaxed) {
+                    self.align_text();
+                }
+                if DOWNLOADING.load(Ordering::Relaxed) {
+                    ui.horizontal(|ui| {
+                        ui.label("下载模型中");
+                        ui.add(ProgressBar::new(DOWNLOADED.load(Ordering::Relaxed) as f32 / FILE_SIZE.load(Ordering::Relaxed) as f32).desired_width(200.0).show_percentage());
+                    });
+                }
+                ui.label(if WHISPER.load(Ordering::Relaxed) { "转换中" } else { "转换结束" });
+                let status = self.whisper_status.lock().unwrap();
+                if !status.is_empty() {
+                    ui.colored_label(Color32::RED, status.as_str());
                 }
             });
+        });
 
-            if ui.button("音频 -> 字幕").clicked() {
-                if !WHISPER.load(Ordering::Relaxed) && !DOWNLOADING.load(Ordering::Relaxed) {
-                    self.whisper();
-                }
-            }
-            if DOWNLOADING.load(Ordering::Relaxed) {
-                ui.horizontal(|ui| {
-                    ui.label("下载模型中");
-                    ui.add(ProgressBar::new(DOWNLOADED.load(Ordering::Relaxed) as f32 / FILE_SIZE.load(Ordering::Relaxed) as f32).desired_width(200.0).show_percentage());
-                });
+        if self.editor.open {
+            self.editor.show(ctx, &self.config.segmentation);
+        }
+    }
+}
+
+pub fn select<T: Copy + PartialEq + Display + ValueEnum>(ui: &mut Ui, label: &str, value: &mut T) -> bool {
+    let mut changed = false;
+    ComboBox::from_label(label)
+        .selected_text(format!("{}", value))
+        .show_ui(ui, |ui| {
+            ui.style_mut().wrap = Some(false);
+            for i in T::value_variants() {
+                changed |= ui.selectable_value(value, *i, format!("{}", *i)).clicked();
             }
-            ui.label(if WHISPER.load(Ordering::Relaxed) { "转换中" } else { "转换结束" });
         });
+    changed
+}
+
+fn preview_style(path: &Path, config: &MergeConfig, [width, height]: [usize; 2]) -> SubtitleStyle {
+    burn_style(path, config, (width as u32, height as u32))
+        .unwrap_or(SubtitleStyle { size: 16, outline: 1.0, shadow: 0.0, margin: 10, ..Default::default() })
+}
+
+fn subtitle_preview(ui: &mut Ui, style: &SubtitleStyle) {
+    let (rect, _) = ui.allocate_exact_size(Vec2::new(ui.available_width(), ui.available_width() * 9.0 / 16.0), Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 0.0, Color32::from_gray(60));
+    paint_subtitle(&painter, rect, style, "字幕预览 Subtitle");
+}
+
+fn paint_subtitle(painter: &Painter, rect: Rect, style: &SubtitleStyle, text: &str) {
+    let scale = rect.height() / 288.0;
+    let font = FontId::proportional(style.size as f32 * scale);
+    let margin = style.margin as f32 * scale;
+    let (x, align_x) = match style.alignment {
+        Position::TopLeft | Position::BottomLeft => (rect.left() + margin, Align::LEFT),
+        Position::TopRight | Position::BottomRight => (rect.right() - margin, Align::RIGHT),
+        _ => (rect.center().x, Align::Center),
+    };
+    let (y, align_y) = match style.alignment {
+        Position::TopLeft | Position::Top | Position::TopRight => (rect.top() + margin, Align::TOP),
+        Position::Center => (rect.center().y, Align::Center),
+        _ => (rect.bottom() - margin, Align::BOTTOM),
+    };
+    let anchor = Align2([align_x, align_y]);
+    let pos = Pos2::new(x, y);
+    let [r, g, b] = style.outline_color;
+    let outline = Color32::from_rgb(r, g, b);
+    if style.shadow > 0.0 {
+        let offset = Vec2::splat(style.shadow * scale);
+        painter.text(pos + offset, anchor, text, font.clone(), Color32::from_black_alpha(160));
+    }
+    let width = style.outline * scale;
+    if width > 0.0 {
+        for (dx, dy) in [(-1.0, -1.0), (0.0, -1.0), (1.0, -1.0), (-1.0, 0.0), (1.0, 0.0), (-1.0, 1.0), (0.0, 1.0), (1.0, 1.0)] {
+            painter.text(pos + Vec2::new(dx, dy) * width, anchor, text, font.clone(), outline);
+        }
     }
-}
\ No newline at end of file
+    let [r, g, b] = style.color;
+    painter.text(pos, anchor, text, font, Color32::from_rgb(r, g, b));
+}
@@ -1,44 +1,662 @@
 use std::env::temp_dir;
 use std::fs::File;
-use std::path::Path;
-use std::process::{Child, Command};
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus};
 use std::process::Stdio;
 use std::sync::atomic::AtomicBool;
 
 use anyhow::{anyhow, Result};
 use audrey::Reader;
+use image::AnimationDecoder;
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::codecs::webp::WebPDecoder;
+
+use crate::config::{Background, MergeConfig, Motion, SlideTiming, SubtitleMode, SubtitleStyle, Visualizer};
+use crate::conv::{Files, Subtitle};
+use crate::whisper::{Format, Transcript};
 
 pub static WHISPER: AtomicBool = AtomicBool::new(false);
 pub static DOWNLOADING: AtomicBool = AtomicBool::new(false);
 pub static MERGE: AtomicBool = AtomicBool::new(false);
+pub static PREVIEW: AtomicBool = AtomicBool::new(false);
+
+#[derive(Clone, PartialEq)]
+pub enum Source {
+    File(PathBuf),
+    Color([u8; 3]),
+    Gradient([u8; 3], [u8; 3]),
+}
+
+impl Source {
+    fn is_animated(&self) -> bool {
+        matches!(self, Self::File(path) if is_animated(path))
+    }
+
+    fn size(&self, config: &MergeConfig) -> Result<(u32, u32)> {
+        Ok(match self {
+            Self::File(path) => config.canvas.size(dimensions(path)?),
+            _ => config.canvas.size((1920, 1080)),
+        })
+    }
+
+    fn input(&self, args: &mut Vec<String>, (width, height): (u32, u32), framerate: u32) -> String {
+        match self {
+            Self::File(path) if is_animated(path) => {
+                args.extend(["-stream_loop", "-1"].map(String::from));
+                path.to_str().unwrap().to_string()
+            }
+            Self::File(path) => {
+                args.extend(["-loop".to_string(), "1".to_string(), "-framerate".to_string(), framerate.to_string()]);
+                path.to_str().unwrap().to_string()
+            }
+            Self::Color([r, g, b]) => {
+                args.extend(["-f", "lavfi"].map(String::from));
+                format!("color=c=0x{r:02x}{g:02x}{b:02x}:s={width}x{height}:r={framerate}")
+            }
+            Self::Gradient([r0, g0, b0], [r1, g1, b1]) => {
+                args.extend(["-f", "lavfi"].map(String::from));
+                format!("gradients=s={width}x{height}:c0=0x{r0:02x}{g0:02x}{b0:02x}:c1=0x{r1:02x}{g1:02x}{b1:02x}:x0=0:y0=0:x1={width}:y1={height}:speed=0:r={framerate}")
+            }
+        }
+    }
+}
+
+pub struct Segment {
+    pub source: Source,
+    pub start: f64,
+    pub end: Option<f64>,
+}
 
-#[inline]
-pub fn merge(audio: &str, image: &str, subtitle: &str, output: &str) -> std::io::Result<Child> {
-    Command::new("ffmpeg")
+pub struct Text {
+    pub file: PathBuf,
+    pub font: String,
+    pub size: u32,
+    pub color: [u8; 3],
+    pub opacity: f32,
+    pub x: String,
+    pub y: String,
+    pub range: Option<(f64, f64)>,
+}
+
+impl Text {
+    pub fn filter(&self) -> String {
+        let [r, g, b] = self.color;
+        let mut filter = format!(
+            "drawtext=textfile={}:font='{}':fontsize={}:fontcolor=0x{:02x}{:02x}{:02x}@{:.2}:borderw=2:bordercolor=black@{:.2}:x={}:y={}",
+            self.file.to_str().unwrap(),
+            self.font,
+            self.size,
+            r,
+            g,
+            b,
+            self.opacity,
+            self.opacity * 0.6,
+            self.x,
+            self.y,
+        );
+        if let Some((start, end)) = self.range {
+            filter += &format!(":enable='between(t,{:.3},{:.3})'", start, end);
+        }
+        filter
+    }
+}
+
+pub fn merge(audio: &str, segments: &[Segment], subtitles: &[Subtitle], texts: &[Text], logo: Option<&Path>, config: &MergeConfig, output: &str) -> Result<Child> {
+    let encoder = &config.encoder;
+    if segments.is_empty() {
+        return Err(anyhow!("no slides to merge"));
+    }
+    let size = segments[0].source.size(config)?;
+    let excerpt;
+    let segments = match config.excerpt {
+        Some((start, length)) => {
+            excerpt = segments
+                .iter()
+                .filter(|s| s.start < start + length && !matches!(s.end, Some(end) if end <= start))
+                .map(|s| Segment { source: s.source.clone(), start: (s.start - start).max(0.0), end: s.end.map(|end| end - start) })
+                .collect::<Vec<_>>();
+            &excerpt[..]
+        }
+        None => segments,
+    };
+    if segments.is_empty() {
+        return Err(anyhow!("preview start is beyond the audio"));
+    }
+    let offset = config.excerpt.map(|(start, _)| start);
+    let burn = subtitles.iter().find(|s| s.mode == SubtitleMode::Burn);
+    let still = config.still
+        && config.ken_burns.motion == Motion::None
+        && config.visualization.kind == Visualizer::None
+        && !segments.iter().any(|s| s.source.is_animated());
+    let dynamic = burn.is_some() || segments.len() > 1 || texts.iter().any(|t| t.range.is_some());
+    let framerate = if still && !dynamic { 1 } else { encoder.framerate };
+    let crossfade = if segments.len() > 1 { config.slideshow.crossfade } else { 0.0 };
+    let mut args = vec!["-y".to_string()];
+    if let Some((start, length)) = config.excerpt {
+        args.extend(["-ss".to_string(), format!("{:.3}", start), "-t".to_string(), format!("{:.3}", length)]);
+    }
+    args.extend(["-i".to_string(), audio.to_string()]);
+    let soft = subtitles
+        .iter()
+        .filter(|s| s.mode == SubtitleMode::Soft)
+        .collect::<Vec<_>>();
+    for subtitle in soft.iter() {
+        if let Some(start) = offset {
+            args.extend(["-ss".to_string(), format!("{:.3}", start)]);
+        }
+        args.extend(["-i".to_string(), subtitle.path.to_str().unwrap().to_string()]);
+    }
+    let first = soft.len() + 1;
+    let mut sources: Vec<&Source> = vec![];
+    let inputs = segments
+        .iter()
+        .map(|segment| match sources.iter().position(|s| **s == segment.source) {
+            Some(i) => i,
+            None => {
+                sources.push(&segment.source);
+                sources.len() - 1
+            }
+        })
+        .collect::<Vec<_>>();
+    let single_frame = |source: &Source| segments.len() > 1 && matches!(source, Source::File(path) if !is_animated(path));
+    for source in sources.iter() {
+        let input = match source {
+            Source::File(path) if single_frame(source) => path.to_str().unwrap().to_string(),
+            _ => source.input(&mut args, size, framerate),
+        };
+        if let (Some(start), Source::File(path)) = (offset, source) {
+            if let Some(length) = Some(path).filter(|path| is_animated(path)).and_then(|path| duration(path).ok()).filter(|d| *d > 0.0) {
+                args.extend(["-ss".to_string(), format!("{:.3}", start % length)]);
+            }
+        }
+        args.extend(["-i".to_string(), input]);
+    }
+    if let Some(logo) = logo {
+        if is_animated(logo) {
+            args.extend(["-stream_loop", "-1"].map(String::from));
+        } else {
+            args.extend(["-loop".to_string(), "1".to_string(), "-framerate".to_string(), framerate.to_string()]);
+        }
+        args.extend(["-i".to_string(), logo.to_str().unwrap().to_string()]);
+    }
+
+    let ken_burns = |source: &Source| config.ken_burns.filter(size, encoder.framerate).filter(|_| !source.is_animated());
+    let mut graph = String::new();
+    if segments.len() == 1 {
+        graph += &config.canvas.filter(&format!("{}:v", first), 0, size);
+        if let Some(ken_burns) = ken_burns(&segments[0].source) {
+            graph += &format!(",{}", ken_burns);
+        }
+    } else {
+        for (i, source) in sources.iter().enumerate() {
+            graph += &config.canvas.filter(&format!("{}:v", first + i), i, size);
+            if single_frame(source) {
+                graph += ",loop=loop=-1:size=1";
+            }
+            let outputs = (0..segments.len()).filter(|j| inputs[*j] == i).map(|j| format!("[p{}]", j)).collect::<Vec<_>>();
+            graph += &format!(",fps={},split={}{};", encoder.framerate, outputs.len(), outputs.concat());
+        }
+        for (i, segment) in segments.iter().enumerate() {
+            let tail = if i == segments.len() - 1 { 1.0 } else { 0.0 };
+            let end = segment.end.unwrap_or(segment.start);
+            graph += &format!("[p{}]trim=start={:.3}:duration={:.3},setpts=PTS-STARTPTS", i, segment.start, end - segment.start + crossfade + tail);
+            if let Some(ken_burns) = ken_burns(&segment.source) {
+                graph += &format!(",{}", ken_burns);
+            }
+            graph += &format!(",fps={},format=yuv420p[s{}];", encoder.framerate, i);
+        }
+        if crossfade > 0.0 {
+            let mut last = "s0".to_string();
+            for (i, segment) in segments.iter().enumerate().skip(1) {
+                graph += &format!("[{}][s{}]xfade=transition=fade:duration={:.3}:offset={:.3}", last, i, crossfade, segment.start);
+                if i < segments.len() - 1 {
+                    last = format!("x{}", i);
+                    graph += &format!("[{}];", last);
+                }
+            }
+        } else {
+            graph += &(0..segments.len()).map(|i| format!("[s{}]", i)).collect::<String>();
+            graph += &format!("concat=n={}:v=1:a=0", segments.len());
+        }
+    }
+    if let Some(visualization) = config.visualization.filter("0:a", size, encoder.framerate) {
+        graph += &format!("[base];{}[viz];[base][viz]overlay={}", visualization, config.visualization.position.overlay(size.1 / 30));
+    }
+    if let Some(start) = offset {
+        graph += &format!(",setpts=PTS+{:.3}/TB", start);
+    }
+    for text in texts {
+        graph += &format!(",{}", text.filter());
+    }
+    if logo.is_some() {
+        let mut logo = config.overlay.logo.filter(&format!("{}:v", first + sources.len()), size);
+        if let Some(start) = offset {
+            logo += &format!(",setpts=PTS+{:.3}/TB", start);
+        }
+        graph += &format!("[main];{}[logo];[main][logo]overlay={}", logo, config.overlay.logo.position.overlay(size.1 / 30));
+    }
+    if let Some(subtitle) = burn {
+        graph += &format!(",subtitles={}", subtitle.path.to_str().unwrap());
+        if let Some(style) = subtitle_style(&subtitle.path, config, size) {
+            graph += &format!(":force_style='{}'", style);
+        }
+    }
+    if offset.is_some() {
+        graph += ",setpts=PTS-STARTPTS";
+    }
+    if still && dynamic {
+        graph += &format!(",mpdecimate=max={}", encoder.framerate);
+    }
+    graph += "[v]";
+    args.extend(["-filter_complex", &graph, "-map", "[v]", "-map", "0:a"].map(String::from));
+    for (i, subtitle) in soft.iter().enumerate() {
+        args.extend([
+            "-map".to_string(),
+            format!("{}:s", i + 1),
+            format!("-c:s:{}", i),
+            encoder.container.subtitle_codec(&subtitle.path).to_string(),
+            format!("-metadata:s:s:{}", i),
+            format!("language={}", subtitle.lang.iso639_2()),
+        ]);
+    }
+    args.extend(encoder.args(still));
+    args.extend(["-shortest".to_string(), output.to_string()]);
+
+    Ok(Command::new("ffmpeg")
+        .args(args)
+        .spawn()?)
+}
+
+pub fn burn_style(path: &Path, config: &MergeConfig, size: (u32, u32)) -> Option<SubtitleStyle> {
+    if Format::from_path(path) == Some(Format::Ass) {
+        None
+    } else if config.subtitle_style.enabled {
+        Some(config.subtitle_style.clone())
+    } else {
+        config.canvas.subtitle_style(size).map(|(size, margin)| SubtitleStyle { size, outline: 1.0, shadow: 0.0, margin, ..Default::default() })
+    }
+}
+
+pub fn subtitle_style(path: &Path, config: &MergeConfig, size: (u32, u32)) -> Option<String> {
+    burn_style(path, config, size).map(|style| match config.subtitle_style.enabled {
+        true => style.force_style(),
+        false => format!("Fontsize={},MarginV={}", style.size, style.margin),
+    })
+}
+
+pub fn slideshow(files: &Files, config: &MergeConfig) -> Result<Vec<Segment>> {
+    let (images, Some(ref audio)) = (&files.images, &files.audio) else {
+        return Err(anyhow!("audio is required"));
+    };
+    if images.len() == 1 {
+        return Ok(vec![Segment { source: Source::File(images[0].path.clone()), start: 0.0, end: None }]);
+    }
+
+    let duration = duration(audio)?;
+    let starts = match config.slideshow.timing {
+        SlideTiming::Even => (0..images.len())
+            .map(|i| (i, i as f64 * duration / images.len() as f64))
+            .collect::<Vec<_>>(),
+        SlideTiming::Manual => {
+            if images.iter().skip(1).all(|s| s.start <= 0.0) {
+                return Err(anyhow!("manual slide timing requires start times"));
+            }
+            let mut starts = images.iter().map(|s| s.start).enumerate().collect::<Vec<_>>();
+            starts.sort_by(|a, b| a.1.total_cmp(&b.1));
+            starts
+        }
+        SlideTiming::Cues => {
+            let subtitle = files.subtitles
+                .iter()
+                .filter(|s| s.mode != SubtitleMode::None)
+                .min_by_key(|s| s.mode != SubtitleMode::Burn)
+                .ok_or_else(|| anyhow!("a subtitle is required for cue timing"))?;
+            Transcript::read_file(&subtitle.path)?
+                .utterances
+                .iter()
+                .enumerate()
+                .map(|(i, u)| (i % images.len(), u.start as f64 / 100.0))
+                .collect()
+        }
+    };
+
+    let gap = config.slideshow.crossfade.max(0.5);
+    let mut segments: Vec<Segment> = vec![];
+    for (i, start) in starts {
+        if start >= duration {
+            break;
+        }
+        let start = match segments.last() {
+            Some(last) if start - last.start < gap => continue,
+            Some(_) => start,
+            None => 0.0,
+        };
+        segments.push(Segment { source: Source::File(images[i].path.clone()), start, end: None });
+    }
+    if segments.is_empty() {
+        return Err(anyhow!("no slide starts before the end of the audio"));
+    }
+    for i in 0..segments.len() {
+        segments[i].end = Some(segments.get(i + 1).map_or(duration, |s| s.start));
+    }
+    Ok(segments)
+}
+
+pub fn generated(audio: &Path, config: &MergeConfig, caches: &mut Vec<PathBuf>) -> Result<(Segment, Vec<Text>)> {
+    let generated = &config.generated;
+    let source = match generated.kind {
+        Background::Color => Source::Color(generated.color),
+        Background::Gradient => Source::Gradient(generated.color, generated.color2),
+        Background::Cover => {
+            let cover = PathBuf::from(format!("{}.png", uuid::Uuid::new_v4()));
+            let status = Command::new("ffmpeg")
+                .args([
+                    "-y",
+                    "-i",
+                    audio.to_str().ok_or_else(|| anyhow!("invalid path"))?,
+                    "-an",
+                    "-frames:v",
+                    "1",
+                    cover.to_str().ok_or_else(|| anyhow!("invalid path"))?,
+                    "-hide_banner",
+                    "-loglevel",
+                    "error",
+                ])
+                .stdin(Stdio::null())
+                .status()?;
+            if !status.success() || !cover.exists() {
+                return Err(anyhow!("no cover art found"));
+            }
+            caches.push(cover.clone());
+            Source::File(cover)
+        }
+    };
+
+    let mut texts = vec![];
+    if generated.text {
+        let (title, artist) = tags(audio)?;
+        let title = title.unwrap_or_else(|| audio.file_stem().unwrap_or_default().to_string_lossy().to_string());
+        for (text, size, y) in [(Some(title), 72, "(h-text_h)/2-h/12"), (artist, 44, "(h-text_h)/2+h/24")] {
+            if let Some(text) = text {
+                texts.push(Text {
+                    file: cache_text(&text, caches)?,
+                    font: "Microsoft YaHei UI".to_string(),
+                    size,
+                    color: generated.text_color,
+                    opacity: 1.0,
+                    x: "(w-text_w)/2".to_string(),
+                    y: y.to_string(),
+                    range: None,
+                });
+            }
+        }
+    }
+    Ok((Segment { source, start: 0.0, end: None }, texts))
+}
+
+pub fn frame(files: &Files, config: &MergeConfig, time: f64) -> Result<PathBuf> {
+    let Some(ref audio) = files.audio else {
+        return Err(anyhow!("audio is required"));
+    };
+    let mut caches = vec![];
+    let frame = frame_cached(audio, files, config, time, &mut caches);
+    for cache in caches {
+        std::fs::remove_file(cache).ok();
+    }
+    frame
+}
+
+fn frame_cached(audio: &Path, files: &Files, config: &MergeConfig, time: f64, caches: &mut Vec<PathBuf>) -> Result<PathBuf> {
+    let mut segment = if files.images.is_empty() {
+        generated(audio, config, caches)?.0
+    } else {
+        slideshow(files, config)?
+            .into_iter()
+            .rev()
+            .find(|s| s.start <= time)
+            .ok_or_else(|| anyhow!("no background"))?
+    };
+    decode_sources(std::slice::from_mut(&mut segment), caches)?;
+    let size = segment.source.size(config)?;
+    let mut args = vec!["-y".to_string()];
+    if let Source::File(ref path) = segment.source {
+        if is_animated(path) {
+            let offset = (time - segment.start) % duration(path)?.max(0.1);
+            args.extend(["-ss".to_string(), format!("{:.3}", offset)]);
+        }
+    }
+    let input = segment.source.input(&mut args, size, 1);
+    let output = temp_dir().join(format!("{}.png", uuid::Uuid::new_v4()));
+    args.extend([
+        "-i".to_string(),
+        input,
+        "-filter_complex".to_string(),
+        config.canvas.filter("0:v", 0, size),
+        "-frames:v".to_string(),
+        "1".to_string(),
+        output.to_str().ok_or_else(|| anyhow!("invalid path"))?.to_string(),
+    ]);
+    args.extend(["-hide_banner", "-loglevel", "error"].map(String::from));
+    let status = Command::new("ffmpeg")
+        .args(args)
+        .stdin(Stdio::null())
+        .status()?;
+    if status.success() && output.exists() {
+        Ok(output)
+    } else {
+        Err(anyhow!("unable to render frame"))
+    }
+}
+
+fn cache_text(text: &str, caches: &mut Vec<PathBuf>) -> Result<PathBuf> {
+    let file = PathBuf::from(format!("{}.txt", uuid::Uuid::new_v4()));
+    std::fs::write(&file, text)?;
+    caches.push(file.clone());
+    Ok(file)
+}
+
+pub fn merge_files(files: &Files, config: &MergeConfig, output: Option<PathBuf>) -> Result<PathBuf> {
+    let Some(ref audio) = files.audio else {
+        return Err(anyhow!("audio is required"));
+    };
+    config.encoder.check()?;
+
+    let mut caches = vec![];
+    let status = merge_cached(audio, files, config, &output, &mut caches);
+    for cache in caches {
+        std::fs::remove_file(cache).ok();
+    }
+    let (status, output) = status?;
+
+    if status.success() {
+        Ok(output)
+    } else {
+        Err(anyhow!("unable to merge files"))
+    }
+}
+
+fn merge_cached(audio: &Path, files: &Files, config: &MergeConfig, output: &Option<PathBuf>, caches: &mut Vec<PathBuf>) -> Result<(ExitStatus, PathBuf)> {
+    let (mut segments, texts) = if files.images.is_empty() {
+        let (segment, texts) = generated(audio, config, caches)?;
+        (vec![segment], texts)
+    } else {
+        (slideshow(files, config)?, vec![])
+    };
+    decode_sources(&mut segments, caches)?;
+    let logo = files.logo.as_deref().map(|logo| decode_webp(logo, caches)).transpose()?;
+
+    let mut texts = texts;
+    let title = &config.overlay.title;
+    if title.enabled && !title.text.is_empty() {
+        let (x, y) = title.position.drawtext();
+        texts.push(Text {
+            file: cache_text(&title.text, caches)?,
+            font: title.font.clone(),
+            size: title.size,
+            color: title.color,
+            opacity: 1.0,
+            x,
+            y,
+            range: Some((title.start, title.end)),
+        });
+    }
+    let watermark = &config.overlay.watermark;
+    if watermark.enabled && !watermark.text.is_empty() {
+        let (x, y) = watermark.position.drawtext();
+        texts.push(Text {
+            file: cache_text(&watermark.text, caches)?,
+            font: watermark.font.clone(),
+            size: watermark.size,
+            color: watermark.color,
+            opacity: watermark.opacity,
+            x,
+            y,
+            range: None,
+        });
+    }
+
+    let mut subtitles = files.subtitles
+        .iter()
+        .filter(|s| s.mode != SubtitleMode::None)
+        .cloned()
+        .collect::<Vec<_>>();
+    let mut burned = false;
+    for subtitle in subtitles.iter_mut().filter(|s| s.mode == SubtitleMode::Burn) {
+        if burned {
+            subtitle.mode = SubtitleMode::Soft;
+            continue;
+        }
+        let cache = PathBuf::from(uuid::Uuid::new_v4().to_string()).with_extension(subtitle.path.extension().unwrap_or_default());
+        std::fs::copy(&subtitle.path, &cache)?;
+        caches.push(cache.clone());
+        subtitle.path = cache;
+        burned = true;
+    }
+    let extension = config.encoder.container.extension();
+    let output = output.clone().unwrap_or_else(|| match config.excerpt {
+        Some(_) => audio.with_extension(format!("preview.{}", extension)),
+        None => audio.with_extension(extension),
+    });
+
+    let status = merge(
+        audio.to_str().ok_or_else(|| anyhow!("invalid path"))?,
+        &segments,
+        &subtitles,
+        &texts,
+        logo.as_deref(),
+        config,
+        output.to_str().ok_or_else(|| anyhow!("invalid path"))?,
+    )?.wait()?;
+    Ok((status, output))
+}
+
+fn decode_webp(path: &Path, caches: &mut Vec<PathBuf>) -> Result<PathBuf> {
+    if !path.extension().and_then(|e| e.to_str()).is_some_and(|e| e.eq_ignore_ascii_case("webp")) {
+        return Ok(path.to_path_buf());
+    }
+    let decoder = WebPDecoder::new(BufReader::new(File::open(path)?))?;
+    if !decoder.has_animation() {
+        return Ok(path.to_path_buf());
+    }
+    let cache = PathBuf::from(format!("{}.gif", uuid::Uuid::new_v4()));
+    caches.push(cache.clone());
+    let mut encoder = GifEncoder::new_with_speed(File::create(&cache)?, 10);
+    encoder.set_repeat(Repeat::Infinite)?;
+    encoder.try_encode_frames(decoder.into_frames())?;
+    Ok(cache)
+}
+
+fn decode_sources(segments: &mut [Segment], caches: &mut Vec<PathBuf>) -> Result<()> {
+    let mut decoded: Vec<(PathBuf, PathBuf)> = vec![];
+    for segment in segments.iter_mut() {
+        let Source::File(ref mut path) = segment.source else {
+            continue;
+        };
+        *path = match decoded.iter().find(|(from, _)| from == path) {
+            Some((_, to)) => to.clone(),
+            None => {
+                let to = decode_webp(path, caches)?;
+                decoded.push((path.clone(), to.clone()));
+                to
+            }
+        };
+    }
+    Ok(())
+}
+
+pub fn tags<P: AsRef<Path>>(path: P) -> Result<(Option<String>, Option<String>)> {
+    let output = Command::new("ffprobe")
         .args([
-            "-y",
-            "-loop",
-            "1",
-            "-framerate",
-            "30",
-            "-i",
-            image,
-            "-i",
-            audio,
-            "-vf",
-            &format!("subtitles={}", subtitle),
-            "-c:v",
-            "libx264",
-            "-c:a",
-            "aac",
-            "-pix_fmt",
-            "yuv420p",
-            "-r",
-            "30",
-            "-shortest",
-            output,
+            "-v",
+            "error",
+            "-show_entries",
+            "format_tags=title,artist",
+            "-of",
+            "default=noprint_wrappers=1",
+            path.as_ref().to_str().ok_or_else(|| anyhow!("invalid path"))?,
+        ])
+        .stdin(Stdio::null())
+        .output()?;
+    let tags = String::from_utf8_lossy(&output.stdout);
+    let tag = |key: &str| {
+        tags.lines()
+            .find_map(|l| l.strip_prefix(&format!("TAG:{}=", key)))
+            .map(|t| t.trim().to_string())
+            .filter(|t| !t.is_empty())
+    };
+    Ok((tag("title"), tag("artist")))
+}
+
+pub fn is_animated<P: AsRef<Path>>(path: P) -> bool {
+    matches!(
+        path.as_ref().extension().and_then(|e| e.to_str()).map(|e| e.to_lowercase()).as_deref(),
+        Some("gif" | "mp4" | "mkv" | "mov" | "webm" | "avi")
+    )
+}
+
+pub fn dimensions<P: AsRef<Path>>(path: P) -> Result<(u32, u32)> {
+    if let Ok(size) = image::image_dimensions(&path) {
+        return Ok(size);
+    }
+    let output = Command::new("ffprobe")
+        .args([
+            "-v",
+            "error",
+            "-select_streams",
+            "v:0",
+            "-show_entries",
+            "stream=width,height",
+            "-of",
+            "csv=s=x:p=0",
+            path.as_ref().to_str().ok_or_else(|| anyhow!("invalid path"))?,
+        ])
+        .stdin(Stdio::null())
+        .output()?;
+    let size = String::from_utf8_lossy(&output.stdout);
+    size.trim()
+        .split_once('x')
+        .and_then(|(w, h)| Some((w.parse().ok()?, h.parse().ok()?)))
+        .ok_or_else(|| anyhow!("unable to get dimensions"))
+}
+
+pub fn duration<P: AsRef<Path>>(path: P) -> Result<f64> {
+    let output = Command::new("ffprobe")
+        .args([
+            "-v",
+            "error",
+            "-show_entries",
+            "format=duration",
+            "-of",
+            "default=noprint_wrappers=1:nokey=1",
+            path.as_ref().to_str().ok_or_else(|| anyhow!("invalid path"))?,
         ])
-        .spawn()
+        .stdin(Stdio::null())
+        .output()?;
+    String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .parse()
+        .map_err(|_| anyhow!("unable to get duration"))
 }
 
 // ffmpeg -i input.mp3 -ar 16000 output.wav
@@ -81,3 +699,47 @@ pub fn read_file<P: AsRef<Path>>(audio_file_path: P) -> Result<Vec<f32>> {
     let audio_buf = use_ffmpeg(&audio_file_path)?;
     Ok(whisper_rs::convert_integer_to_float_audio(&audio_buf))
 }
+
+pub const SILENCE: f32 = 0.005;
+
+pub fn energy(samples: &[f32]) -> Vec<f32> {
+    samples
+        .chunks(160)
+        .map(|c| (c.iter().map(|s| s * s).sum::<f32>() / c.len() as f32).sqrt())
+        .collect()
+}
+
+pub fn speech(samples: &[f32]) -> Vec<bool> {
+    let energy = energy(samples);
+    if energy.is_empty() {
+        return vec![];
+    }
+    let mut sorted = energy.clone();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let floor = sorted[sorted.len() / 5];
+    let peak = sorted[sorted.len() * 19 / 20];
+    let threshold = floor + (peak - floor) * 0.25;
+    energy.iter().map(|e| *e > SILENCE && *e >= threshold).collect()
+}
+
+pub fn regions(speech: &[bool], padding: usize, gap: usize, min: usize) -> Vec<(usize, usize)> {
+    let mut regions: Vec<(usize, usize)> = vec![];
+    let mut i = 0;
+    while i < speech.len() {
+        if !speech[i] {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        while i < speech.len() && speech[i] {
+            i += 1;
+        }
+        let (start, end) = (start.saturating_sub(padding), (i + padding).min(speech.len()));
+        match regions.last_mut() {
+            Some(last) if start <= last.1 + gap => last.1 = end,
+            _ => regions.push((start, end)),
+        }
+    }
+    regions.retain(|(start, end)| end - start >= min);
+    regions
+}
@@ -1,13 +1,16 @@
+use std::borrow::Cow;
+use std::cmp::{max, min};
+use std::fmt::Display;
 use std::fs::File;
 use std::io::{Error, ErrorKind, Write};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::time::{Duration, Instant};
 
 use anyhow::anyhow;
 use serde::{Deserialize, Serialize};
 use whisper_rs::{FullParams, SamplingStrategy, WhisperContext};
 
-use crate::config::{Language, Model};
+use crate::config::{Hallucination, Language, Model, Segmentation};
 use crate::utils;
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -17,7 +20,7 @@ pub struct Transcript {
     pub word_utterances: Option<Vec<Utterance>>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Utterance {
     pub start: i64,
     pub end: i64,
@@ -27,6 +30,8 @@ pub struct Utterance {
 pub struct Whisper {
     ctx: WhisperContext,
     lang: Language,
+    pub hallucination: Hallucination,
+    pub vad: bool,
 }
 
 impl Whisper {
@@ -35,6 +40,8 @@ impl Whisper {
         Ok(Self {
             ctx: WhisperContext::new(model.get_path().to_str().unwrap()).map_err(|_| Error::from(ErrorKind::InvalidData))?,
             lang,
+            hallucination: Hallucination::Keep,
+            vad: false,
         })
     }
 
@@ -50,10 +57,26 @@ impl Whisper {
         params.set_language(Some(<&str>::from(self.lang)));
 
         let audio = utils::read_file(audio)?;
+        let speech = match self.vad || self.hallucination != Hallucination::Keep {
+            true => utils::speech(&audio),
+            false => vec![],
+        };
+        let regions = match self.vad {
+            true => utils::regions(&speech, 20, 50, 25),
+            false => vec![],
+        };
+        if self.vad && regions.is_empty() {
+            return Err(anyhow!("No segments found"));
+        }
+        let samples = match regions.is_empty() {
+            true => Cow::Borrowed(&audio[..]),
+            false => regions.iter().flat_map(|(start, end)| audio[start * 160..(end * 160).min(audio.len())].iter().copied()).collect(),
+        };
+        let timeline = Timeline::new(&regions);
 
         let st = Instant::now();
         let mut state = self.ctx.create_state().expect("failed to create state");
-        state.full(params, &audio).expect("failed to transcribe");
+        state.full(params, &samples).expect("failed to transcribe");
 
         let num_segments = state.full_n_segments().expect("failed to get segments");
         if num_segments == 0 {
@@ -73,7 +96,7 @@ impl Whisper {
                 .full_get_segment_t1(s)
                 .map_err(|e| anyhow!("failed to get segment due to {:?}", e))?;
 
-            utterances.push(Utterance { text, start, end });
+            utterances.push(Utterance { text, start: timeline.map(start), end: timeline.map_end(end) });
 
             if !word_timestamps {
                 continue;
@@ -97,37 +120,390 @@ impl Whisper {
 
                 words.push(Utterance {
                     text,
-                    start: token_data.t0,
-                    end: token_data.t1,
+                    start: timeline.map(token_data.t0),
+                    end: timeline.map_end(token_data.t1),
                 });
             }
         }
 
-        Ok(Transcript {
+        let mut transcript = Transcript {
             utterances,
             processing_time: Instant::now().duration_since(st),
             word_utterances: if word_timestamps { Some(words) } else { None },
-        })
+        };
+        transcript.filter(&audio, &speech, self.hallucination);
+        Ok(transcript)
+    }
+}
+
+struct Timeline(Vec<(i64, i64, i64)>);
+
+impl Timeline {
+    fn new(regions: &[(usize, usize)]) -> Self {
+        let mut position = 0;
+        Self(
+            regions
+                .iter()
+                .map(|(start, end)| {
+                    let length = (end - start) as i64;
+                    position += length;
+                    (position - length, *start as i64, length)
+                })
+                .collect(),
+        )
+    }
+
+    fn map(&self, time: i64) -> i64 {
+        self.resolve(time, |position| position <= time)
+    }
+
+    fn map_end(&self, time: i64) -> i64 {
+        self.resolve(time, |position| position < time)
+    }
+
+    fn resolve(&self, time: i64, f: impl Fn(i64) -> bool) -> i64 {
+        match self.0.iter().rev().find(|(position, _, _)| f(*position)).or(self.0.first()) {
+            Some((position, start, length)) => start + (time - position).clamp(0, *length),
+            None => time,
+        }
+    }
+}
+
+fn normalize(text: &str) -> Vec<char> {
+    text.chars()
+        .filter(|c| !c.is_whitespace() && !is_punctuation(*c))
+        .flat_map(|c| c.to_lowercase())
+        .collect()
+}
+
+fn is_loop(text: &[char]) -> bool {
+    (2..=text.len() / 4).any(|period| (period..text.len()).all(|i| text[i] == text[i % period]))
+}
+
+fn coverage(utterance: &Utterance, frames: &[bool]) -> f64 {
+    let start = (utterance.start.max(0) as usize).min(frames.len());
+    let end = (utterance.end.max(0) as usize).clamp(start, frames.len());
+    if start == end {
+        return if start == frames.len() { 0.0 } else { 1.0 };
     }
+    frames[start..end].iter().filter(|f| **f).count() as f64 / (end - start) as f64
 }
 
-#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, clap::ValueEnum)]
 pub enum Format {
+    #[clap(name = "lrc")]
     Lrc,
+    #[clap(name = "srt")]
     Srt,
+    #[clap(name = "vtt")]
     Vtt,
+    #[clap(name = "ass")]
+    Ass,
+}
+
+impl Display for Format {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let key = match self {
+            Self::Lrc => "LRC",
+            Self::Srt => "SRT",
+            Self::Vtt => "VTT",
+            Self::Ass => "ASS",
+        };
+        write!(f, "{key}")
+    }
+}
+
+impl Format {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Lrc => "lrc",
+            Self::Srt => "srt",
+            Self::Vtt => "vtt",
+            Self::Ass => "ass",
+        }
+    }
+
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Self> {
+        match path.as_ref().extension()?.to_str()?.to_lowercase().as_str() {
+            "lrc" => Some(Self::Lrc),
+            "srt" => Some(Self::Srt),
+            "vtt" => Some(Self::Vtt),
+            "ass" | "ssa" => Some(Self::Ass),
+            _ => None,
+        }
+    }
+}
+
+const ASS_HEADER: &str = "[Script Info]\n\
+    ScriptType: v4.00+\n\
+    PlayResX: 384\n\
+    PlayResY: 288\n\
+    WrapStyle: 0\n\n\
+    [V4+ Styles]\n\
+    Format: Name, Fontname, Fontsize, PrimaryColour, SecondaryColour, OutlineColour, BackColour, Bold, Italic, Underline, StrikeOut, ScaleX, ScaleY, Spacing, Angle, BorderStyle, Outline, Shadow, Alignment, MarginL, MarginR, MarginV, Encoding\n";
+
+const ASS_EVENTS: &str = "\n[Events]\n\
+    Format: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\n";
+
+pub fn parse_time(time: &str) -> Option<i64> {
+    let time = time.trim();
+    let (clock, fraction) = match time.rsplit_once(['.', ',']) {
+        Some((clock, fraction)) => (clock, fraction),
+        None => (time, "0"),
+    };
+    let seconds = clock
+        .split(':')
+        .try_fold(0, |acc, part| part.trim().parse::<i64>().ok().map(|n| acc * 60 + n))?;
+    let centis = format!("{:0<2}", fraction).get(..2)?.parse::<i64>().ok()?;
+    Some(seconds * 100 + centis)
+}
+
+pub fn is_cjk(c: char) -> bool {
+    matches!(c, '\u{3040}'..='\u{30ff}' | '\u{3400}'..='\u{4dbf}' | '\u{4e00}'..='\u{9fff}' | '\u{ac00}'..='\u{d7af}' | '\u{f900}'..='\u{faff}' | '\u{ff00}'..='\u{ffef}' | '\u{3000}'..='\u{303f}')
+}
+
+pub fn is_punctuation(c: char) -> bool {
+    c.is_ascii_punctuation() || matches!(c, '，' | '。' | '、' | '；' | '：' | '！' | '？' | '…' | '」' | '』' | '）' | '”')
+}
+
+pub fn width(text: &str) -> usize {
+    text.chars().filter(|c| *c != '\n').map(|c| if is_cjk(c) { 2 } else { 1 }).sum()
+}
+
+fn wrap(words: &[Utterance], chars_per_line: usize) -> Vec<&[Utterance]> {
+    let mut lines = vec![];
+    let mut start = 0;
+    let mut used = 0;
+    for (i, word) in words.iter().enumerate() {
+        let w = width(if i == start { word.text.trim_start() } else { &word.text });
+        if i > start && used + w > chars_per_line {
+            lines.push(&words[start..i]);
+            start = i;
+            used = width(word.text.trim_start());
+        } else {
+            used += w;
+        }
+    }
+    if start < words.len() {
+        lines.push(&words[start..]);
+    }
+    if lines.len() == 2 {
+        let line_width = |line: &[Utterance]| width(line.iter().map(|w| w.text.as_str()).collect::<String>().trim());
+        let split = (1..words.len())
+            .filter(|&i| line_width(&words[..i]) <= chars_per_line && line_width(&words[i..]) <= chars_per_line)
+            .min_by_key(|&i| {
+                let punctuation = words[i - 1].text.trim_end().ends_with(is_punctuation);
+                line_width(&words[..i]).abs_diff(line_width(&words[i..])) * 2 + if punctuation { 0 } else { 10 }
+            });
+        if let Some(split) = split {
+            return vec![&words[..split], &words[split..]];
+        }
+    }
+    lines
+}
+
+pub fn rewrap(text: &str, chars_per_line: usize) -> String {
+    let words = Transcript {
+        processing_time: Duration::ZERO,
+        utterances: vec![Utterance {
+            start: 0,
+            end: 0,
+            text: text.lines().map(str::trim).fold(String::new(), |text, line| match text.ends_with(is_cjk) || line.starts_with(is_cjk) {
+                true => text + line,
+                false => text + " " + line,
+            }),
+        }],
+        word_utterances: None,
+    }.words();
+    wrap(&words, chars_per_line)
+        .iter()
+        .map(|line| line.iter().map(|w| w.text.as_str()).collect::<String>().trim().to_string())
+        .collect::<Vec<_>>()
+        .join("\n")
 }
 
 impl Transcript {
-    pub fn write_file<P: AsRef<Path>>(&self, audio: P, format: Format) {
-        let (path, subtitle) = match format {
-            Format::Lrc => (audio.as_ref().with_extension("lrc"), self.to_lrc()),
-            Format::Srt => (audio.as_ref().with_extension("srt"), self.to_srt()),
-            Format::Vtt => (audio.as_ref().with_extension("vtt"), self.to_vtt()),
+    pub fn read_file<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        let format = Format::from_path(&path).ok_or_else(|| anyhow!("unsupported subtitle format"))?;
+        let subtitle = std::fs::read_to_string(&path)?;
+        let subtitle = subtitle.trim_start_matches('\u{feff}');
+        let utterances = match format {
+            Format::Lrc => Self::parse_lrc(subtitle),
+            Format::Srt | Format::Vtt => Self::parse_cues(subtitle, format == Format::Vtt),
+            Format::Ass => Self::parse_ass(subtitle),
+        };
+
+        Ok(Transcript {
+            processing_time: Duration::ZERO,
+            utterances,
+            word_utterances: None,
+        })
+    }
+
+    fn parse_cues(subtitle: &str, vtt: bool) -> Vec<Utterance> {
+        let mut utterances = vec![];
+        let mut lines = subtitle.lines().map(str::trim_end);
+        while let Some(line) = lines.next() {
+            let Some((start, end)) = line.split_once("-->") else {
+                continue;
+            };
+            let end = end.split_whitespace().next().unwrap_or_default();
+            let (Some(start), Some(end)) = (parse_time(start), parse_time(end)) else {
+                continue;
+            };
+            let text = lines
+                .by_ref()
+                .take_while(|l| !l.trim().is_empty())
+                .map(|l| if vtt { l.strip_prefix("- ").unwrap_or(l) } else { l })
+                .collect::<Vec<_>>()
+                .join("\n");
+            utterances.push(Utterance { start, end, text });
+        }
+        utterances
+    }
+
+    fn parse_lrc(subtitle: &str) -> Vec<Utterance> {
+        let mut tags = vec![];
+        for line in subtitle.lines() {
+            let mut rest = line.trim();
+            let mut times = vec![];
+            while let Some((tag, tail)) = rest.strip_prefix('[').and_then(|r| r.split_once(']')) {
+                match parse_time(tag) {
+                    Some(time) => times.push(time),
+                    None => break,
+                }
+                rest = tail;
+            }
+            tags.extend(times.into_iter().map(|time| (time, rest.trim().to_string())));
+        }
+        tags.sort_by_key(|(time, _)| *time);
+
+        tags.iter()
+            .enumerate()
+            .filter(|(_, (_, text))| !text.is_empty())
+            .map(|(i, (start, text))| Utterance {
+                start: *start,
+                end: tags.get(i + 1).map_or(*start + 500, |(end, _)| *end),
+                text: text.clone(),
+            })
+            .collect()
+    }
+
+    fn parse_ass(subtitle: &str) -> Vec<Utterance> {
+        subtitle
+            .lines()
+            .filter_map(|line| line.strip_prefix("Dialogue:"))
+            .filter_map(|line| {
+                let fields = line.splitn(10, ',').collect::<Vec<_>>();
+                let text = fields.get(9)?;
+                let mut plain = String::new();
+                let mut depth = 0;
+                for c in text.chars() {
+                    match c {
+                        '{' => depth += 1,
+                        '}' => depth -= 1,
+                        _ if depth == 0 => plain.push(c),
+                        _ => {}
+                    }
+                }
+                Some(Utterance {
+                    start: parse_time(fields[1])?,
+                    end: parse_time(fields[2])?,
+                    text: plain.replace("\\N", "\n").replace("\\n", "\n").replace("\\h", " "),
+                })
+            })
+            .collect()
+    }
+
+    pub fn write_file<P: AsRef<Path>>(&self, audio: P, format: Format) -> anyhow::Result<PathBuf> {
+        let path = audio.as_ref().with_extension(format.extension());
+        self.save(&path, format)?;
+        Ok(path)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P, format: Format) -> anyhow::Result<()> {
+        let subtitle = match format {
+            Format::Lrc => self.to_lrc(),
+            Format::Srt => self.to_srt(),
+            Format::Vtt => self.to_vtt(),
+            Format::Ass => self.to_ass(),
         };
-        if let Ok(mut file) = File::create(path) {
-            file.write_all(subtitle.as_bytes()).unwrap();
+        File::create(path)?.write_all(subtitle.as_bytes())?;
+        Ok(())
+    }
+
+    pub fn filter(&mut self, samples: &[f32], speech: &[bool], mode: Hallucination) {
+        if mode == Hallucination::Keep {
+            return;
+        }
+        let loud = utils::energy(samples).iter().map(|e| *e > utils::SILENCE).collect::<Vec<_>>();
+        let mut previous = vec![];
+        let mut repeats = 0;
+        let mut dropped = vec![];
+        for utterance in self.utterances.iter_mut() {
+            let text = normalize(&utterance.text);
+            if !text.is_empty() {
+                repeats = if text == previous { repeats + 1 } else { 1 };
+            }
+            let silent = coverage(utterance, &loud) < 0.1;
+            let quiet = coverage(utterance, speech) < 0.1;
+            let hallucination = text.is_empty() || silent || repeats >= 3 || ((repeats == 2 || is_loop(&text)) && quiet);
+            if !text.is_empty() {
+                previous = text;
+            }
+            if !hallucination {
+                continue;
+            }
+            match mode {
+                Hallucination::Flag => utterance.text = format!("[?] {}", utterance.text.trim()),
+                _ => dropped.push((utterance.start, utterance.end)),
+            }
+        }
+        if dropped.is_empty() {
+            return;
+        }
+        let kept = |u: &Utterance| !dropped.iter().any(|(start, end)| u.start >= *start && u.end <= *end);
+        self.utterances.retain(kept);
+        if let Some(ref mut words) = self.word_utterances {
+            words.retain(kept);
+        }
+    }
+
+    pub fn retime<F: Fn(i64) -> i64>(&mut self, f: F) {
+        let retime = |utterance: &mut Utterance| {
+            let end = f(utterance.end);
+            utterance.start = f(utterance.start).max(0);
+            utterance.end = end.max(utterance.start);
+            end > 0
+        };
+        self.utterances.retain_mut(retime);
+        if let Some(ref mut words) = self.word_utterances {
+            words.retain_mut(retime);
+        }
+    }
+
+    pub fn shift(&mut self, offset: i64) {
+        self.retime(|t| t + offset);
+    }
+
+    pub fn stretch(&mut self, (from_a, to_a): (i64, i64), (from_b, to_b): (i64, i64)) -> anyhow::Result<()> {
+        if from_a == from_b {
+            return Err(anyhow!("sync points must be at different times"));
         }
+        let scale = (to_b - to_a) as f64 / (from_b - from_a) as f64;
+        if scale <= 0.0 {
+            return Err(anyhow!("sync points must keep the same order"));
+        }
+        self.retime(|t| to_a + ((t - from_a) as f64 * scale).round() as i64);
+        Ok(())
+    }
+
+    pub fn convert_framerate(&mut self, from: f64, to: f64) -> anyhow::Result<()> {
+        if from <= 0.0 || to <= 0.0 {
+            return Err(anyhow!("invalid framerate"));
+        }
+        self.retime(|t| (t as f64 * from / to).round() as i64);
+        Ok(())
     }
 
     pub fn to_lrc(&self) -> String {
@@ -195,4 +571,335 @@ impl Transcript {
                     )
             })
     }
-}
\ No newline at end of file
+
+    pub fn stack(&self, translation: &Transcript) -> Transcript {
+        let mut lines = vec![vec![]; self.utterances.len()];
+        let mut standalone = vec![];
+        for t in translation.utterances.iter() {
+            let best = self.utterances
+                .iter()
+                .enumerate()
+                .map(|(i, u)| (i, min(u.end, t.end) - max(u.start, t.start)))
+                .filter(|(_, overlap)| *overlap > 0)
+                .max_by_key(|(_, overlap)| *overlap);
+            match best {
+                Some((i, _)) => lines[i].push(t.text.trim()),
+                None => standalone.push(Utterance {
+                    start: t.start,
+                    end: t.end,
+                    text: format!("\n{}", t.text.trim()),
+                }),
+            }
+        }
+
+        let mut utterances = self.utterances
+            .iter()
+            .zip(lines)
+            .map(|(u, lines)| Utterance {
+                start: u.start,
+                end: u.end,
+                text: format!("{}\n{}", u.text.trim(), lines.join(" ")),
+            })
+            .chain(standalone)
+            .collect::<Vec<_>>();
+        utterances.sort_by_key(|u| u.start);
+
+        Transcript {
+            processing_time: self.processing_time + translation.processing_time,
+            utterances,
+            word_utterances: None,
+        }
+    }
+
+    fn words(&self) -> Vec<Utterance> {
+        let mut words: Vec<Utterance> = vec![];
+        if let Some(ref tokens) = self.word_utterances {
+            for token in tokens.iter().filter(|t| !t.text.trim().is_empty()) {
+                let cjk = token.text.trim_start().starts_with(is_cjk);
+                match words.last_mut() {
+                    Some(word) if !token.text.starts_with(char::is_whitespace) && !cjk && !word.text.ends_with(is_cjk) => {
+                        word.text += &token.text;
+                        word.end = token.end;
+                    }
+                    _ => words.push(token.clone()),
+                }
+            }
+            return words;
+        }
+
+        for utterance in self.utterances.iter() {
+            let mut pieces: Vec<String> = vec![];
+            let mut space = false;
+            for c in utterance.text.chars() {
+                if c.is_whitespace() {
+                    space = true;
+                    continue;
+                }
+                let after_cjk = pieces.last().is_some_and(|p| p.ends_with(|p: char| is_cjk(p) && p.is_alphanumeric()));
+                if pieces.is_empty() || space || (c.is_alphanumeric() && (is_cjk(c) || after_cjk)) {
+                    pieces.push(if space && !pieces.is_empty() { " ".to_string() } else { String::new() });
+                }
+                pieces.last_mut().unwrap().push(c);
+                space = false;
+            }
+            let total = pieces.iter().map(|p| p.trim().chars().count()).sum::<usize>().max(1) as i64;
+            let span = utterance.end - utterance.start;
+            let mut count = 0;
+            for piece in pieces {
+                let start = utterance.start + span * count / total;
+                count += piece.trim().chars().count() as i64;
+                words.push(Utterance { start, end: utterance.start + span * count / total, text: piece });
+            }
+        }
+        words
+    }
+
+    pub fn resegment(&self, limits: &Segmentation) -> Transcript {
+        let join = |words: &[Utterance]| words.iter().map(|w| w.text.as_str()).collect::<String>().trim().to_string();
+        let fits = |words: &[Utterance]| wrap(words, limits.chars_per_line).len() <= limits.lines;
+        let fast = |words: &[Utterance]| width(&join(words)) as f64 > limits.cps * (words[words.len() - 1].end - words[0].start) as f64 / 100.0;
+        let duration = (limits.duration * 100.0) as i64;
+
+        let mut cues: Vec<Vec<Utterance>> = vec![];
+        let mut current: Vec<Utterance> = vec![];
+        for word in self.words() {
+            while let Some(last) = current.last() {
+                let mut candidate = current.clone();
+                candidate.push(word.clone());
+                if word.end - current[0].start <= duration && word.start - last.end < 150 && fits(&candidate) && (!fast(&candidate) || fast(&current)) {
+                    break;
+                }
+                let k = current
+                    .iter()
+                    .rposition(|w| w.text.trim_end().ends_with(is_punctuation))
+                    .map(|k| k + 1)
+                    .filter(|&k| k < current.len() && k >= current.len() / 3)
+                    .unwrap_or(current.len());
+                let rest = current.split_off(k);
+                cues.push(std::mem::replace(&mut current, rest));
+            }
+            let strong = word.text.trim_end().ends_with(['.', '!', '?', '。', '！', '？', '…']);
+            current.push(word);
+            if strong {
+                cues.push(std::mem::take(&mut current));
+            }
+        }
+        if !current.is_empty() {
+            cues.push(current);
+        }
+
+        let mut utterances = cues
+            .iter()
+            .map(|cue| Utterance {
+                start: cue[0].start,
+                end: cue[cue.len() - 1].end,
+                text: wrap(cue, limits.chars_per_line).iter().map(|line| join(line)).collect::<Vec<_>>().join("\n"),
+            })
+            .collect::<Vec<_>>();
+        for i in 0..utterances.len() {
+            let minimum = (width(&utterances[i].text) as f64 / limits.cps * 100.0).ceil() as i64;
+            let limit = utterances.get(i + 1).map(|u| u.start).unwrap_or(i64::MAX);
+            let utterance = &mut utterances[i];
+            utterance.end = utterance.end.max((utterance.start + minimum).min(limit).min(utterance.start + duration));
+        }
+
+        Transcript {
+            processing_time: self.processing_time,
+            utterances,
+            word_utterances: None,
+        }
+    }
+
+    pub fn write_bilingual<P: AsRef<Path>>(&self, audio: P) -> anyhow::Result<()> {
+        for (extension, subtitle) in [("bilingual.srt", self.to_srt()), ("bilingual.ass", self.to_bilingual_ass())] {
+            File::create(audio.as_ref().with_extension(extension))?.write_all(subtitle.as_bytes())?;
+        }
+        Ok(())
+    }
+
+    pub fn to_ass(&self) -> String {
+        self.ass(
+            &["Style: Default,Microsoft YaHei UI,18,&H00FFFFFF,&H000000FF,&H00000000,&H80000000,0,0,0,0,100,100,0,0,1,1,0,2,10,10,10,1"],
+            |text| text.trim().replace('\n', "\\N"),
+        )
+    }
+
+    pub fn to_bilingual_ass(&self) -> String {
+        self.ass(
+            &[
+                "Style: Default,Microsoft YaHei UI,18,&H00FFFFFF,&H000000FF,&H00000000,&H80000000,0,0,0,0,100,100,0,0,1,1,0,2,10,10,10,1",
+                "Style: Translation,Microsoft YaHei UI,12,&H0000D7FF,&H000000FF,&H00000000,&H80000000,0,0,0,0,100,100,0,0,1,1,0,2,10,10,10,1",
+            ],
+            |text| match text.rsplit_once('\n') {
+                Some((original, translation)) if translation.trim().is_empty() => original.trim().replace('\n', "\\N"),
+                Some((original, translation)) if original.trim().is_empty() => format!("{{\\rTranslation}}{}", translation.trim()),
+                Some((original, translation)) => format!("{}\\N{{\\rTranslation}}{}", original.trim().replace('\n', "\\N"), translation.trim()),
+                None => text.trim().to_string(),
+            },
+        )
+    }
+
+    fn ass<F: Fn(&str) -> String>(&self, styles: &[&str], text: F) -> String {
+        let header = String::from(ASS_HEADER) + &styles.join("\n") + "\n" + ASS_EVENTS;
+        self.word_utterances
+            .as_ref()
+            .unwrap_or(&self.utterances)
+            .iter()
+            .fold(header, |ass, fragment| {
+                ass +
+                    &format!(
+                        "Dialogue: 0,{}:{:02}:{:02}.{:02},{}:{:02}:{:02}.{:02},Default,,0,0,0,,{}\n",
+                        fragment.start / 100 / 3600,
+                        fragment.start / 100 % 3600 / 60,
+                        fragment.start / 100 % 60,
+                        fragment.start % 100,
+                        fragment.end / 100 / 3600,
+                        fragment.end / 100 % 3600 / 60,
+                        fragment.end / 100 % 60,
+                        fragment.end % 100,
+                        text(&fragment.text)
+                    )
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utterance(start: i64, end: i64, text: &str) -> Utterance {
+        Utterance { start, end, text: text.to_string() }
+    }
+
+    fn transcript(utterances: Vec<Utterance>, words: Option<Vec<Utterance>>) -> Transcript {
+        Transcript { processing_time: Duration::ZERO, utterances, word_utterances: words }
+    }
+
+    fn texts(utterances: &[Utterance]) -> Vec<&str> {
+        utterances.iter().map(|u| u.text.as_str()).collect()
+    }
+
+    #[test]
+    fn words_join_tokens_without_leading_space() {
+        let tokens = vec![utterance(0, 10, " Hel"), utterance(10, 20, "lo"), utterance(20, 30, ","), utterance(30, 50, " world")];
+        let words = transcript(vec![], Some(tokens)).words();
+        assert_eq!(texts(&words), [" Hello,", " world"]);
+        assert_eq!((words[0].start, words[0].end), (0, 30));
+    }
+
+    #[test]
+    fn words_split_cjk_per_character() {
+        let tokens = vec![utterance(0, 10, "你好"), utterance(10, 20, "世界")];
+        let words = transcript(vec![], Some(tokens)).words();
+        assert_eq!(texts(&words), ["你好", "世界"]);
+
+        let words = transcript(vec![utterance(0, 400, "你好 hello世界")], None).words();
+        assert_eq!(texts(&words), ["你", "好", " hello", "世", "界"]);
+        assert_eq!(words[0].start, 0);
+        assert_eq!(words[4].end, 400);
+        assert!(words.windows(2).all(|w| w[0].end == w[1].start));
+    }
+
+    #[test]
+    fn wrap_fits_exactly_at_limit() {
+        let tokens = vec![utterance(0, 10, " aaaa"), utterance(10, 20, " bbbb."), utterance(20, 30, " cc"), utterance(30, 40, " d")];
+        let words = transcript(vec![], Some(tokens)).words();
+        let lines = wrap(&words, 10);
+        assert_eq!(lines.iter().map(|l| l.len()).collect::<Vec<_>>(), [2, 2]);
+    }
+
+    #[test]
+    fn wrap_counts_cjk_as_double_width() {
+        assert_eq!(rewrap("一二三四五六", 6), "一二三\n四五六");
+        assert_eq!(rewrap("一二三，四五六七", 8), "一二三，\n四五六七");
+        assert_eq!(rewrap("short line", 42), "short line");
+    }
+
+    #[test]
+    fn resegment_respects_limits() {
+        let limits = Segmentation { enabled: true, chars_per_line: 10, lines: 1, duration: 7.0, cps: 20.0 };
+        let text = "one two three four five six seven eight";
+        let resegmented = transcript(vec![utterance(0, 400, text)], None).resegment(&limits);
+        for u in resegmented.utterances.iter() {
+            assert!(!u.text.contains('\n'));
+            assert!(width(&u.text) <= 10, "{:?}", u.text);
+        }
+        assert_eq!(texts(&resegmented.utterances).join(" "), text);
+    }
+
+    #[test]
+    fn resegment_breaks_on_duration_and_strong_punctuation() {
+        let limits = Segmentation { enabled: true, chars_per_line: 42, lines: 2, duration: 2.0, cps: 50.0 };
+        let words = vec![utterance(0, 100, " First."), utterance(100, 200, " Second"), utterance(200, 300, " third"), utterance(300, 400, " fourth")];
+        let resegmented = transcript(vec![], Some(words)).resegment(&limits);
+        assert_eq!(texts(&resegmented.utterances), ["First.", "Second third", "fourth"]);
+    }
+
+    #[test]
+    fn resegment_breaks_before_reading_speed_is_exceeded() {
+        let limits = Segmentation { enabled: true, chars_per_line: 42, lines: 2, duration: 7.0, cps: 5.0 };
+        let words = vec![utterance(0, 100, " aaaa"), utterance(100, 200, " bbbb"), utterance(200, 210, " cccccccc"), utterance(300, 500, " dd")];
+        let resegmented = transcript(vec![], Some(words)).resegment(&limits);
+        assert_eq!(texts(&resegmented.utterances), ["aaaa bbbb", "cccccccc dd"]);
+        assert_eq!(resegmented.utterances[0].end, 200);
+    }
+
+    #[test]
+    fn resegment_cjk_without_spaces() {
+        let limits = Segmentation { enabled: true, chars_per_line: 8, lines: 1, duration: 7.0, cps: 20.0 };
+        let resegmented = transcript(vec![utterance(0, 400, "今天天气很好我们出去玩")], None).resegment(&limits);
+        assert_eq!(texts(&resegmented.utterances).concat(), "今天天气很好我们出去玩");
+        assert!(resegmented.utterances.iter().all(|u| width(&u.text) <= 8));
+    }
+
+    #[test]
+    fn filter_keeps_repeated_lines_until_long_runs_or_silence() {
+        let mut samples = vec![0.1; 800 * 160];
+        samples.resize(1000 * 160, 0.0);
+        let cues = || {
+            vec![
+                utterance(0, 200, "Hold on"),
+                utterance(200, 400, "Hold on"),
+                utterance(400, 600, "hold on!"),
+                utterance(600, 700, "Let go"),
+                utterance(700, 800, "la la la la"),
+                utterance(850, 950, "Thanks for watching"),
+            ]
+        };
+
+        let mut kept = transcript(cues(), None);
+        kept.filter(&samples, &[], Hallucination::Keep);
+        assert_eq!(kept.utterances.len(), 6);
+
+        let mut dropped = transcript(cues(), None);
+        dropped.filter(&samples, &utils::speech(&samples), Hallucination::Drop);
+        assert_eq!(texts(&dropped.utterances), ["Hold on", "Hold on", "Let go", "la la la la"]);
+    }
+
+    #[test]
+    fn timeline_maps_boundaries_to_their_region() {
+        let timeline = Timeline::new(&[(100, 200), (500, 550)]);
+        assert_eq!(timeline.map(0), 100);
+        assert_eq!(timeline.map(100), 500);
+        assert_eq!(timeline.map_end(100), 200);
+        assert_eq!(timeline.map_end(0), 100);
+        assert_eq!(timeline.map(120), 520);
+        assert_eq!(timeline.map_end(150), 550);
+        assert_eq!(timeline.map_end(170), 550);
+
+        let empty = Timeline::new(&[]);
+        assert_eq!((empty.map(100), empty.map_end(100)), (100, 100));
+    }
+
+    #[test]
+    fn bilingual_ass_keeps_multiline_originals_in_one_dialogue() {
+        let original = transcript(vec![utterance(0, 300, "line one\nline two"), utterance(300, 500, "alone")], None);
+        let translation = transcript(vec![utterance(0, 300, "translated"), utterance(600, 700, "extra")], None);
+        let ass = original.stack(&translation).to_bilingual_ass();
+        let dialogues = ass.lines().filter(|l| l.starts_with("Dialogue:")).collect::<Vec<_>>();
+        assert_eq!(dialogues.len(), 3);
+        assert!(dialogues[0].ends_with(",line one\\Nline two\\N{\\rTranslation}translated"));
+        assert!(dialogues[1].ends_with(",alone"));
+        assert!(dialogues[2].ends_with(",{\\rTranslation}extra"));
+    }
+}